@@ -0,0 +1,469 @@
+use crate::commands::ollama::{backend, with_persona, MESSAGE_LIMIT};
+use crate::commands::voice::{check_idle, get_voice_info, join_voice};
+use crate::conversation::{Message, Role};
+use crate::listen::{strip_wake_word, transcribe, wav, Sessions, Utterances};
+use crate::splitter::split_message;
+use crate::{Context, Data, Error, NooqieError};
+
+use log::{debug, error, info};
+
+use poise::{
+    async_trait,
+    serenity_prelude::{
+        self as serenity, ButtonStyle, ChannelId, Colour, ComponentInteractionCollector,
+        CreateActionRow, CreateAllowedMentions, CreateButton, CreateEmbed,
+        CreateInteractionResponse, CreateInteractionResponseMessage, CreateMessage, EditMessage,
+        GuildId, MessageId, UserId,
+    },
+    CreateReply,
+};
+
+use songbird::{
+    driver::{Channels, DecodeMode, SampleRate},
+    events::{CoreEvent, Event, EventContext, EventHandler as VoiceEventHandler},
+    Songbird,
+};
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tokio::sync::mpsc;
+
+/// Length of the audio in a single voice tick.
+const TICK: Duration = Duration::from_millis(20);
+
+/// How often the consent buttons check whether the session is still on.
+const CONSENT_POLL: Duration = Duration::from_secs(30);
+
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only = true,
+    subcommands("listen_start", "listen_stop"),
+    subcommand_required,
+    category = "Voice",
+    help_text_fn = listen_help
+)]
+pub async fn listen(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+pub fn listen_help() -> String {
+    String::from("answers questions asked out loud in your voice channel, by people who allow it")
+}
+
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only = true,
+    rename = "start",
+    help_text_fn = listen_start_help
+)]
+pub async fn listen_start(ctx: Context<'_>) -> Result<(), Error> {
+    let (guild_id, voice_channel) = get_voice_info(ctx).await?;
+    let manager = songbird::get(ctx.serenity_context())
+        .await
+        .ok_or(NooqieError::NoVoiceClient)?;
+
+    let Some(session) = ctx.data().listening.start(guild_id, ctx.channel_id()) else {
+        ctx.say("I'm already listening, `listen stop` to stop")
+            .await?;
+        return Ok(());
+    };
+    if let Err(error) = start_receiving(ctx, &manager, guild_id, voice_channel, session).await {
+        ctx.data().listening.stop(guild_id);
+        return Err(error);
+    }
+    info!("{}: listening in {}", guild_id, voice_channel);
+    update_idle(ctx, guild_id).await;
+
+    let wake_word = ctx.data().config.get().listen.wake_word.clone();
+    let allow_id = format!("{}allow", ctx.id());
+    let deny_id = format!("{}deny", ctx.id());
+    let embed = CreateEmbed::new()
+        .title("🎙️ Listening")
+        .description(format!(
+            "I'm listening in <#{voice_channel}>, but only to people who allow it below.\n\n\
+             What they say is transcribed and then thrown away. Questions starting with \
+             **{wake_word}** are answered by the LLM, and the question and answer are posted \
+             in this channel. No audio is kept.\n\n\
+             Anyone can end this with `listen stop`."
+        ))
+        .colour(Colour::RED);
+    let reply =
+        CreateReply::default()
+            .embed(embed.clone())
+            .components(vec![CreateActionRow::Buttons(vec![
+                CreateButton::new(&allow_id)
+                    .label("Listen to me")
+                    .style(ButtonStyle::Success),
+                CreateButton::new(&deny_id)
+                    .label("Stop listening to me")
+                    .style(ButtonStyle::Secondary),
+            ])]);
+    let message = ctx.send(reply).await?.message().await?.id;
+
+    tokio::spawn(collect_consent(
+        ctx.serenity_context().clone(),
+        ctx.data().listening.clone(),
+        (guild_id, session),
+        (ctx.channel_id(), message),
+        (allow_id, deny_id),
+        embed,
+    ));
+    Ok(())
+}
+
+pub fn listen_start_help() -> String {
+    String::from("starts listening in your voice channel, everyone has to allow it for themselves")
+}
+
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only = true,
+    rename = "stop",
+    help_text_fn = listen_stop_help
+)]
+pub async fn listen_stop(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(NooqieError::NotInGuild)?;
+    if !ctx.data().listening.stop(guild_id) {
+        return Err(NooqieError::NotListening);
+    }
+    if let Some(manager) = songbird::get(ctx.serenity_context()).await {
+        stop_receiving(&manager, guild_id).await;
+    }
+    info!("{}: stopped listening", guild_id);
+    update_idle(ctx, guild_id).await;
+    ctx.say("stopped listening").await?;
+    Ok(())
+}
+
+pub fn listen_stop_help() -> String {
+    String::from("stops listening in this server")
+}
+
+async fn update_idle(ctx: Context<'_>, guild_id: GuildId) {
+    let timeout = ctx.data().config.get().voice.idle_timeout();
+    check_idle(
+        ctx.serenity_context(),
+        &ctx.data().idle,
+        &ctx.data().listening,
+        timeout,
+        guild_id,
+        None,
+    )
+    .await;
+}
+
+/// Joins `channel_id`, has songbird decode what people say there and hands
+/// it to a `Receiver` for session `session`.
+async fn start_receiving(
+    ctx: Context<'_>,
+    manager: &Songbird,
+    guild_id: GuildId,
+    channel_id: serenity::ChannelId,
+    session: u64,
+) -> Result<(), Error> {
+    join_voice(manager, guild_id, channel_id).await?;
+    let handler_lock = manager.get(guild_id).ok_or(NooqieError::NotConnected)?;
+    let mut handler = handler_lock.lock().await;
+
+    let voice = handler
+        .config()
+        .clone()
+        .decode_mode(DecodeMode::Decode)
+        .decode_channels(Channels::Mono)
+        .decode_sample_rate(SampleRate::Hz16000);
+    handler.set_config(voice);
+
+    let config = ctx.data().config.get();
+    let receiver = Receiver {
+        state: Arc::new(ReceiverState {
+            ctx: ctx.serenity_context().clone(),
+            data: ctx.data().clone(),
+            guild_id,
+            session,
+            speakers: Mutex::new(HashMap::new()),
+            utterances: Mutex::new(Utterances::new(
+                config.listen.threshold,
+                config.listen.silence(),
+                config.listen.min_speech(),
+                config.listen.max_utterance(),
+            )),
+        }),
+    };
+    for event in [
+        CoreEvent::SpeakingStateUpdate,
+        CoreEvent::VoiceTick,
+        CoreEvent::ClientDisconnect,
+    ] {
+        handler.add_global_event(event.into(), receiver.clone());
+    }
+    Ok(())
+}
+
+/// Stops decoding received audio in `guild_id`, the receiver of the ended
+/// session removes itself on the next voice event.
+pub async fn stop_receiving(manager: &Songbird, guild_id: GuildId) {
+    if let Some(handler_lock) = manager.get(guild_id) {
+        let mut handler = handler_lock.lock().await;
+        let voice = handler.config().clone().decode_mode(DecodeMode::Decrypt);
+        handler.set_config(voice);
+    }
+}
+
+/// Records who presses the consent buttons on the `listen start` message
+/// until the session ends, then removes the buttons.
+async fn collect_consent(
+    ctx: serenity::Context,
+    sessions: Sessions,
+    (guild_id, session): (GuildId, u64),
+    (channel_id, message_id): (ChannelId, MessageId),
+    (allow_id, deny_id): (String, String),
+    embed: CreateEmbed,
+) {
+    while sessions.channel(guild_id, session).is_some() {
+        let (allow, deny) = (allow_id.clone(), deny_id.clone());
+        let Some(press) = ComponentInteractionCollector::new(&ctx)
+            .message_id(message_id)
+            .filter(move |press| press.data.custom_id == allow || press.data.custom_id == deny)
+            .timeout(CONSENT_POLL)
+            .await
+        else {
+            continue;
+        };
+
+        let allow = press.data.custom_id == allow_id;
+        let content = if !sessions.set_consent(guild_id, session, press.user.id, allow) {
+            "I'm not listening anymore"
+        } else if allow {
+            debug!("{}: {} allowed listening", guild_id, press.user.id);
+            "I'll listen to you until listening stops"
+        } else {
+            debug!("{}: {} disallowed listening", guild_id, press.user.id);
+            "I won't listen to you anymore"
+        };
+        let response = CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new()
+                .content(content)
+                .ephemeral(true),
+        );
+        if let Err(error) = press.create_response(&ctx, response).await {
+            error!("failed to answer consent button: {}", error);
+        }
+    }
+
+    let ended = EditMessage::new()
+        .embed(
+            embed
+                .title("🎙️ Stopped listening")
+                .colour(Colour::DARK_GREY),
+        )
+        .components(Vec::new());
+    if let Err(error) = channel_id.edit_message(&ctx, message_id, ended).await {
+        error!("failed to update listen message: {}", error);
+    }
+}
+
+/// Splits what consenting people say into utterances and answers the ones
+/// starting with the wake word.
+#[derive(Clone)]
+struct Receiver {
+    state: Arc<ReceiverState>,
+}
+
+struct ReceiverState {
+    ctx: serenity::Context,
+    data: Data,
+    guild_id: GuildId,
+    session: u64,
+    speakers: Mutex<HashMap<u32, UserId>>,
+    utterances: Mutex<Utterances>,
+}
+
+#[async_trait]
+impl VoiceEventHandler for Receiver {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        let state = &self.state;
+        if state
+            .data
+            .listening
+            .channel(state.guild_id, state.session)
+            .is_none()
+        {
+            return Some(Event::Cancel);
+        }
+
+        match ctx {
+            EventContext::SpeakingStateUpdate(speaking) => {
+                if let Some(user_id) = speaking.user_id {
+                    let user_id = UserId::new(user_id.0);
+                    state
+                        .speakers
+                        .lock()
+                        .unwrap()
+                        .insert(speaking.ssrc, user_id);
+                }
+            }
+            EventContext::ClientDisconnect(disconnect) => {
+                let mut speakers = state.speakers.lock().unwrap();
+                let user_id = UserId::new(disconnect.user_id.0);
+                speakers.retain(|ssrc, user| {
+                    if *user == user_id {
+                        state.utterances.lock().unwrap().forget(*ssrc);
+                    }
+                    *user != user_id
+                });
+            }
+            EventContext::VoiceTick(tick) => {
+                let mut finished = Vec::new();
+                {
+                    let speakers = state.speakers.lock().unwrap();
+                    let mut utterances = state.utterances.lock().unwrap();
+                    for (ssrc, data) in &tick.speaking {
+                        let Some(&user_id) = speakers.get(ssrc) else {
+                            continue;
+                        };
+                        if !state.data.listening.has_consented(
+                            state.guild_id,
+                            state.session,
+                            user_id,
+                        ) {
+                            utterances.forget(*ssrc);
+                            continue;
+                        }
+                        let Some(voice) = &data.decoded_voice else {
+                            continue;
+                        };
+                        if let Some(samples) = utterances.push(*ssrc, voice) {
+                            finished.push((user_id, samples));
+                        }
+                    }
+                    for ssrc in &tick.silent {
+                        if let Some(samples) = utterances.silence(*ssrc, TICK) {
+                            if let Some(&user_id) = speakers.get(ssrc) {
+                                finished.push((user_id, samples));
+                            }
+                        }
+                    }
+                }
+                for (user_id, samples) in finished {
+                    let state = state.clone();
+                    tokio::spawn(async move {
+                        if let Err(error) = answer_utterance(&state, user_id, samples).await {
+                            error!(
+                                "{}: failed to answer {}: {}",
+                                state.guild_id, user_id, error
+                            );
+                        }
+                    });
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+}
+
+/// Transcribes what `user_id` said and, when it starts with the wake word,
+/// posts the question and the answer of the LLM in the session's channel.
+async fn answer_utterance(
+    state: &ReceiverState,
+    user_id: UserId,
+    samples: Vec<i16>,
+) -> Result<(), Error> {
+    let config = state.data.config.get();
+    let transcript = transcribe(
+        &state.data.http,
+        &config.listen.url,
+        &config.listen.model,
+        wav(&samples),
+    )
+    .await?;
+    let Some(question) = strip_wake_word(&transcript, &config.listen.wake_word) else {
+        return Ok(());
+    };
+    if question.is_empty() {
+        return Ok(());
+    }
+    let Some(channel_id) = state.data.listening.channel(state.guild_id, state.session) else {
+        return Ok(());
+    };
+    debug!("{}: {} asked '{}'", state.guild_id, user_id, question);
+
+    let mentions = CreateAllowedMentions::new();
+    channel_id
+        .send_message(
+            &state.ctx,
+            CreateMessage::new()
+                .content(format!("🎙️ <@{user_id}>: {question}"))
+                .allowed_mentions(mentions.clone()),
+        )
+        .await?;
+    let content = match ask(state, user_id, channel_id, &question).await {
+        Ok(anwser) => split_message(&anwser, MESSAGE_LIMIT),
+        Err(error) => {
+            if error.is_internal() {
+                error!(
+                    "{}: failed to answer {}: {}",
+                    state.guild_id, user_id, error
+                );
+            }
+            vec![error.to_string()]
+        }
+    };
+    for chunk in content {
+        channel_id
+            .send_message(
+                &state.ctx,
+                CreateMessage::new()
+                    .content(chunk)
+                    .allowed_mentions(mentions.clone()),
+            )
+            .await?;
+    }
+    Ok(())
+}
+
+/// Asks the LLM `question` as part of the conversation in `channel_id` and
+/// records the answer there.
+async fn ask(
+    state: &ReceiverState,
+    user_id: UserId,
+    channel_id: ChannelId,
+    question: &str,
+) -> Result<String, Error> {
+    let data = &state.data;
+    let config = data.config.get();
+    let guild_id = Some(state.guild_id);
+    let timeout = config.ollama.context_timeout();
+    let mut messages = data.conversations.history(channel_id, timeout)?;
+    messages.push(Message::new(Role::User, question));
+    let messages = with_persona(data, guild_id, channel_id, messages)?;
+    let model = data.model(guild_id)?;
+
+    let ticket = data.scheduler.admit(user_id, guild_id, &config.limits)?;
+    let _permit = ticket.start(config.limits.concurrency).await;
+    let (sender, mut receiver) = mpsc::unbounded_channel::<String>();
+    let anwser = backend(&config.backend(guild_id), data.http.clone())
+        .chat(model, messages, None, sender)
+        .await;
+    let mut tokens: u64 = 0;
+    while receiver.try_recv().is_ok() {
+        tokens += 1;
+    }
+    data.scheduler.record(state.guild_id, tokens);
+    let anwser = anwser.inspect_err(|_| data.scheduler.refund(user_id))?;
+    data.conversations.record(
+        channel_id,
+        question,
+        &anwser,
+        timeout,
+        config.ollama.max_turns(),
+    )?;
+    Ok(anwser)
+}
@@ -1,3 +1,7 @@
+pub mod listen;
+pub mod model;
 pub mod ollama;
+pub mod persona;
+pub mod prefix;
 pub mod utils;
 pub mod voice;
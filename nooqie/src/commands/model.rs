@@ -0,0 +1,154 @@
+use log::{debug, error};
+
+use crate::commands::ollama::guild_backend;
+use crate::{Context, Error, NooqieError};
+
+#[poise::command(
+    prefix_command,
+    slash_command,
+    subcommands("model_list", "model_use", "model_info"),
+    subcommand_required,
+    aliases("models"),
+    help_text_fn = model_help
+)]
+pub async fn model(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+pub fn model_help() -> String {
+    String::from("selects the model used by llm")
+}
+
+/// Whether the model tag `name` is meant by `model`, which may omit `:latest`.
+fn is_model(name: &str, model: &str) -> bool {
+    name == model || name.strip_suffix(":latest") == Some(model)
+}
+
+async fn autocomplete_model<'a>(
+    ctx: Context<'_>,
+    partial: &'a str,
+) -> impl Iterator<Item = String> + 'a {
+    let models = match guild_backend(ctx).list_models().await {
+        Ok(models) => models,
+        Err(error) => {
+            error!("failed to list models: {error}");
+            Vec::new()
+        }
+    };
+    models
+        .into_iter()
+        .map(|model| model.name)
+        .filter(move |name| name.starts_with(partial))
+}
+
+#[poise::command(
+    prefix_command,
+    slash_command,
+    rename = "list",
+    help_text_fn = model_list_help
+)]
+pub async fn model_list(ctx: Context<'_>) -> Result<(), Error> {
+    let models = guild_backend(ctx).list_models().await?;
+    if models.is_empty() {
+        ctx.say("no models installed").await?;
+        return Ok(());
+    }
+
+    let active = ctx.data().model(ctx.guild_id())?;
+    let mut list = String::new();
+    for model in models {
+        let marker = if is_model(&model.name, &active) {
+            " (active)"
+        } else {
+            ""
+        };
+        let mut line = format!(
+            "**{}**{marker} {} {}",
+            model.name, model.details.parameter_size, model.details.quantization_level,
+        );
+        if model.size > 0 {
+            line.push_str(&format!(" {:.1} GB", model.size as f64 / 1e9));
+        }
+        line.push('\n');
+        if list.chars().count() + line.chars().count() > 2000 {
+            break;
+        }
+        list.push_str(&line);
+    }
+
+    ctx.say(list).await?;
+    Ok(())
+}
+
+pub fn model_list_help() -> String {
+    String::from("lists the models available on the LLM server")
+}
+
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only = true,
+    rename = "use",
+    required_permissions = "MANAGE_GUILD",
+    help_text_fn = model_use_help
+)]
+pub async fn model_use(
+    ctx: Context<'_>,
+    #[description = "Model name"]
+    #[autocomplete = "autocomplete_model"]
+    name: String,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(NooqieError::NotInGuild)?;
+
+    let name = guild_backend(ctx)
+        .list_models()
+        .await?
+        .into_iter()
+        .find(|model| is_model(&model.name, &name))
+        .ok_or(NooqieError::UnknownModel(name))?
+        .name;
+
+    ctx.data().models.set(guild_id, &name)?;
+    debug!("{}: using model '{}'", guild_id, name);
+    ctx.say(format!("using model `{name}`")).await?;
+    Ok(())
+}
+
+pub fn model_use_help() -> String {
+    String::from("selects the model used in this server")
+}
+
+#[poise::command(
+    prefix_command,
+    slash_command,
+    rename = "info",
+    help_text_fn = model_info_help
+)]
+pub async fn model_info(
+    ctx: Context<'_>,
+    #[description = "Model name"]
+    #[autocomplete = "autocomplete_model"]
+    name: Option<String>,
+) -> Result<(), Error> {
+    let name = match name {
+        Some(name) => name,
+        None => ctx.data().model(ctx.guild_id())?,
+    };
+
+    let info = guild_backend(ctx).show_model(name.clone()).await?;
+
+    let parameters: String = match info.parameters.trim() {
+        "" => String::from("defaults"),
+        parameters => parameters.chars().take(1500).collect(),
+    };
+    ctx.say(format!(
+        "**{name}**\nfamily: {}\nparameters: {}\nquantization: {}\n```\n{parameters}\n```",
+        info.details.family, info.details.parameter_size, info.details.quantization_level,
+    ))
+    .await?;
+    Ok(())
+}
+
+pub fn model_info_help() -> String {
+    String::from("shows the details and parameters of a model")
+}
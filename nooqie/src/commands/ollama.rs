@@ -1,32 +1,170 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+
 use log::{debug, error, warn};
 
-use poise::serenity_prelude::OnlineStatus;
-use poise::serenity_prelude::{ActivityData, CreateMessage};
-use poise::CreateReply;
+use poise::serenity_prelude::{
+    self as serenity, Attachment, ButtonStyle, ChannelId, ComponentInteraction,
+    ComponentInteractionCollector, CreateActionRow, CreateAttachment, CreateButton,
+    CreateInteractionResponse, CreateInteractionResponseMessage, CreateMessage, EditMessage,
+    GuildId, MessageId, MessageReference, UserId,
+};
+use poise::{async_trait, CreateReply, ReplyHandle};
 
-use regex::Regex;
-use reqwest::Client;
+use reqwest::{Client, StatusCode};
 
 use serde::{Deserialize, Serialize};
 
-use std::{env, vec};
+use std::time::{Duration, Instant};
+
+use tokio::sync::mpsc::{self, UnboundedSender};
+
+use crate::commands::voice::{get_voice_info, speak};
+use crate::config::{Api, BackendConfig};
+use crate::conversation::{Message, Role};
+use crate::generations::GenerationHandle;
+use crate::replies::{Rating, Reply};
+use crate::scheduler::{Permit, Ticket};
+use crate::splitter::split_message;
+use crate::tracks::truncate;
+use crate::tts::{speakable, synthesize};
+use crate::{Context, Data, Error, NooqieError};
+
+/// Maximum number of replies followed when building history from a reply chain.
+const MAX_REPLY_CHAIN: usize = 20;
+
+/// Maximum number of images sent along with a prompt.
+const MAX_IMAGES: usize = 4;
+
+/// Images larger than this many bytes are not downloaded.
+const MAX_IMAGE_SIZE: u32 = 20 * 1024 * 1024;
+
+/// Discord rejects messages longer than this many characters.
+pub const MESSAGE_LIMIT: usize = 2000;
+
+/// The streamed reply is edited after this many tokens or this much time,
+/// whichever comes first. Serenity queues edits that would hit the rate limit.
+const EDIT_TOKENS: usize = 40;
+const EDIT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How often the position in line is updated while waiting for the LLM.
+const QUEUE_POLL: Duration = Duration::from_secs(2);
+
+/// Appended to an answer that was stopped before it was complete.
+const STOPPED: &str = "*(cut off)*";
+
+/// Ids of the buttons under finished answers. They hold no state, the
+/// answer is looked up by the message the button is on.
+const REGENERATE_ID: &str = "llm-regenerate";
+const CONTINUE_ID: &str = "llm-continue";
+const GOOD_ID: &str = "llm-good";
+const BAD_ID: &str = "llm-bad";
+
+/// Asked to make the model extend its previous answer.
+const CONTINUE_PROMPT: &str = "Continue your previous answer exactly where it stopped.";
+
+/// Shown when a button is pressed under an answer that is no longer stored.
+const FORGOTTEN: &str = "I don't remember that answer anymore";
+
+#[derive(Serialize, Deserialize)]
+pub struct ChatRequest {
+    pub model: String,
+    pub messages: Vec<Message>,
+    pub stream: bool,
+    /// Sampling options of Ollama.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub options: Option<ChatOptions>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ChatOptions {
+    pub seed: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct TagsResponse {
+    pub models: Vec<ModelTag>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ModelTag {
+    pub name: String,
+    #[serde(default)]
+    pub size: u64,
+    #[serde(default)]
+    pub details: ModelDetails,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct ModelDetails {
+    #[serde(default)]
+    pub family: String,
+    #[serde(default)]
+    pub parameter_size: String,
+    #[serde(default)]
+    pub quantization_level: String,
+    #[serde(default)]
+    pub families: Option<Vec<String>>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ShowRequest {
+    pub model: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ShowResponse {
+    #[serde(default)]
+    pub parameters: String,
+    #[serde(default)]
+    pub template: String,
+    #[serde(default)]
+    pub details: ModelDetails,
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+}
+
+impl ShowResponse {
+    /// Whether the model takes images. Older Ollama versions don't report
+    /// capabilities, there a vision encoder shows up in the families.
+    pub fn supports_vision(&self) -> bool {
+        self.capabilities.iter().any(|c| c == "vision")
+            || self
+                .details
+                .families
+                .iter()
+                .flatten()
+                .any(|family| family == "clip" || family == "mllama")
+    }
+}
 
-use crate::{Context, Error};
+/// A single line of the NDJSON stream returned by `/api/chat`.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ChatChunk {
+    Response(ChatResponse),
+    Error { error: String },
+}
 
 #[derive(Serialize, Deserialize)]
-struct OllamaResponse {
-    model: String,
-    created_at: String,
-    response: String,
-    done: bool,
-    done_reason: String,
-    context: Vec<u64>,
-    total_duration: u64,
-    load_duration: u64,
-    prompt_eval_count: u64,
-    prompt_eval_duration: u64,
-    eval_count: u64,
-    eval_duration: u64,
+pub struct ChatResponse {
+    pub model: String,
+    pub created_at: String,
+    pub message: Message,
+    pub done: bool,
+    #[serde(default)]
+    pub done_reason: Option<String>,
+    #[serde(default)]
+    pub total_duration: u64,
+    #[serde(default)]
+    pub load_duration: u64,
+    #[serde(default)]
+    pub prompt_eval_count: u64,
+    #[serde(default)]
+    pub prompt_eval_duration: u64,
+    #[serde(default)]
+    pub eval_count: u64,
+    #[serde(default)]
+    pub eval_duration: u64,
 }
 
 #[poise::command(
@@ -39,140 +177,1295 @@ struct OllamaResponse {
 )]
 pub async fn llm(
     ctx: Context<'_>,
-    #[autocomplete = "poise::builtins::autocomplete_command"]
+    #[description = "Image to ask about"] image: Option<Attachment>,
+    #[description = "What to ask"]
+    #[rest]
+    prompt: String,
+) -> Result<(), Error> {
+    let images = images(ctx, image).await?;
+    answer(ctx, prompt, images).await?;
+    Ok(())
+}
+
+pub fn llm_help() -> String {
+    String::from(
+        "queries offline local Ollama instance, images attached to the message or the one it \
+         replies to are shown to vision models",
+    )
+}
+
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only = true,
+    aliases("say", "tell"),
+    broadcast_typing = true,
+    category = "Voice",
+    help_text_fn = ask_help
+)]
+pub async fn ask(
+    ctx: Context<'_>,
+    #[description = "Question to answer out loud"]
     #[rest]
     msg: Option<String>,
 ) -> Result<(), Error> {
-    let ser_ctx: &poise::serenity_prelude::Context = ctx.serenity_context();
-    let mut status: OnlineStatus = OnlineStatus::DoNotDisturb;
-    let mut activity: ActivityData = ActivityData::custom("thinking...");
-    ctx.serenity_context().set_presence(Some(activity), status);
+    let prompt = msg.ok_or(NooqieError::MissingArgument("prompt"))?;
+    let (guild_id, channel_id) = get_voice_info(ctx).await?;
+
+    let Some(anwser) = answer(ctx, prompt, Vec::new()).await? else {
+        return Ok(());
+    };
+    let tts = ctx.data().config.get().tts.clone();
+    let text = speakable(&anwser, tts.max_length);
+    if text.is_empty() {
+        return Ok(());
+    }
+    let audio = synthesize(&tts.command, &text).await?;
+    speak(ctx, guild_id, channel_id, audio).await
+}
+
+pub fn ask_help() -> String {
+    String::from("answers like llm and reads the answer out in your voice channel")
+}
+
+/// Streams the answer to `prompt` into a reply and records it in the
+/// channel's conversation. Base64 encoded `images` are sent along with the
+/// prompt, which fails when the model can't see them. Returns the answer, or
+/// `None` when the model failed to give one or the answer was stopped.
+async fn answer(
+    ctx: Context<'_>,
+    prompt: String,
+    images: Vec<String>,
+) -> Result<Option<String>, Error> {
+    let timeout = ctx.data().config.get().ollama.context_timeout();
+    let chain = reply_chain(ctx).await;
+    let mut messages = if chain.is_empty() {
+        ctx.data()
+            .conversations
+            .history(ctx.channel_id(), timeout)?
+    } else {
+        chain
+    };
+    messages.push(Message::new(Role::User, prompt.as_str()));
+
+    let request = Reply {
+        guild_id: ctx.guild_id(),
+        channel_id: ctx.channel_id(),
+        model: ctx.data().model(ctx.guild_id())?,
+        prompt,
+        messages,
+        answer: String::new(),
+    };
+    generate(Origin::Command(ctx), request, images, Turn::New).await
+}
+
+/// How a generated answer fits into the conversation.
+enum Turn {
+    /// Answers a new prompt.
+    New,
+    /// Replaces the earlier answer to the same prompt, with another seed.
+    Regenerate(String),
+    /// Carries on with the earlier answer where it stopped.
+    Continue(String),
+}
+
+/// What an answer is generated for: a command, or a button under an earlier
+/// answer.
+enum Origin<'a> {
+    Command(Context<'a>),
+    Button(&'a serenity::Context, &'a Data, &'a ComponentInteraction),
+}
+
+impl<'a> Origin<'a> {
+    fn serenity(&self) -> &'a serenity::Context {
+        match self {
+            Origin::Command(ctx) => ctx.serenity_context(),
+            Origin::Button(ctx, _, _) => ctx,
+        }
+    }
+
+    fn data(&self) -> &'a Data {
+        match self {
+            Origin::Command(ctx) => ctx.data(),
+            Origin::Button(_, data, _) => data,
+        }
+    }
+
+    fn user_id(&self) -> UserId {
+        match self {
+            Origin::Command(ctx) => ctx.author().id,
+            Origin::Button(_, _, press) => press.user.id,
+        }
+    }
+
+    /// Unique id of the invocation, prefixing the ids of its buttons.
+    fn id(&self) -> u64 {
+        match self {
+            Origin::Command(ctx) => ctx.id(),
+            Origin::Button(_, _, press) => press.id.get(),
+        }
+    }
+
+    /// Sends the message the answer is streamed into.
+    async fn placeholder(
+        &self,
+        components: Vec<CreateActionRow>,
+    ) -> Result<ReplyTarget<'a>, Error> {
+        match self {
+            Origin::Command(ctx) => {
+                let builder = CreateReply::default()
+                    .content("...")
+                    .components(components)
+                    .reply(true);
+                Ok(ReplyTarget::Command(*ctx, ctx.send(builder).await?))
+            }
+            Origin::Button(ctx, _, press) => {
+                let builder = CreateMessage::new()
+                    .content("...")
+                    .components(components)
+                    .reference_message(&*press.message);
+                let message = press.channel_id.send_message(ctx, builder).await?;
+                Ok(ReplyTarget::Channel(ctx, message.channel_id, message.id))
+            }
+        }
+    }
+}
 
-    let prompt = match msg {
-        Some(prompt) => prompt,
+/// Generates the answer to `request` and streams it into a new reply, with
+/// buttons to regenerate, continue and rate it once done. `images` are sent
+/// along with the last message, and `turn` decides whether the answer is
+/// added to the conversation or revises an earlier one. Waits in line for
+/// the LLM while showing the position in the reply. Requests that fail
+/// don't cost a cooldown.
+async fn generate(
+    origin: Origin<'_>,
+    request: Reply,
+    images: Vec<String>,
+    turn: Turn,
+) -> Result<Option<String>, Error> {
+    let data = origin.data();
+    let user_id = origin.user_id();
+    let config = data.config.get();
+    let ticket = data
+        .scheduler
+        .admit(user_id, request.guild_id, &config.limits)?;
+    let result = stream_answer(origin, request, images, turn, ticket).await;
+    if result.is_err() {
+        data.scheduler.refund(user_id);
+    }
+    result
+}
+
+/// Generates the answer to `request` once `ticket` is up, see `generate`.
+async fn stream_answer(
+    origin: Origin<'_>,
+    mut request: Reply,
+    images: Vec<String>,
+    turn: Turn,
+    ticket: Ticket,
+) -> Result<Option<String>, Error> {
+    let data = origin.data();
+    let config = data.config.get();
+    let backend = backend(&config.backend(request.guild_id), data.http.clone());
+    check_vision(backend.as_ref(), &request.model, &images).await?;
+
+    debug!("{}: prompt '{}'", request.channel_id, request.prompt);
+
+    let generation = data.generations.start(request.channel_id, origin.user_id());
+    let stop_id = format!("{}stop", origin.id());
+    let stop_button = vec![CreateActionRow::Buttons(vec![CreateButton::new(&stop_id)
+        .label("Stop")
+        .style(ButtonStyle::Secondary)])];
+    let stop = stop_requested(origin.serenity(), &generation, &stop_id, origin.user_id());
+    tokio::pin!(stop);
+
+    let target = origin.placeholder(stop_button.clone()).await?;
+    let _permit = match ticket.try_start(config.limits.concurrency) {
+        Some(permit) => permit,
+        None => tokio::select! {
+            permit = wait_in_line(&target, &ticket, config.limits.concurrency) => permit,
+            _ = &mut stop => {
+                debug!("{}: stopped while waiting in line", request.channel_id);
+                target.edit(STOPPED.to_string(), Some(Vec::new())).await?;
+                return Ok(None);
+            }
+        },
+    };
+    drop(ticket);
+
+    let _thinking = data.presence.thinking(origin.serenity());
+
+    let mut messages = request.messages.clone();
+    if let Turn::Continue(previous) = &turn {
+        messages.push(Message::new(Role::Assistant, previous.as_str()));
+        messages.push(Message::new(Role::User, CONTINUE_PROMPT));
+    }
+    let seed = matches!(turn, Turn::Regenerate(_)).then(rand::random);
+    if let Some(message) = messages.last_mut() {
+        message.images = images;
+    }
+    let messages = with_persona(data, request.guild_id, request.channel_id, messages)?;
+
+    let mut reply = StreamedReply::new(target, stop_button);
+    let (sender, mut receiver) = mpsc::unbounded_channel::<String>();
+
+    let mut tokens: u64 = 0;
+    let mut partial = String::new();
+    // Stopping drops the request, which closes the connection to the server.
+    let anwser = tokio::select! {
+        (anwser, _) = async {
+            tokio::join!(backend.chat(request.model.clone(), messages, seed, sender), async {
+                while let Some(token) = receiver.recv().await {
+                    tokens += 1;
+                    partial.push_str(&token);
+                    reply.append(&token).await;
+                    while let Ok(token) = receiver.try_recv() {
+                        tokens += 1;
+                        partial.push_str(&token);
+                        reply.append(&token).await;
+                    }
+                    reply.update().await;
+                }
+            })
+        } => Some(anwser),
+        _ = &mut stop => None,
+    };
+    if let Some(guild_id) = request.guild_id {
+        data.scheduler.record(guild_id, tokens);
+    }
+
+    let anwser = match anwser {
         None => {
-            warn!("no prompt provided");
-            return Ok(());
+            debug!("{}: stopped '{}'", request.channel_id, partial);
+            if !partial.trim().is_empty() {
+                request.answer = partial;
+                reply.append(" ").await;
+            }
+            reply.append(STOPPED).await;
+            Ok(None)
+        }
+        Some(Ok(anwser)) => {
+            debug!("{}: anwser '{}'", request.channel_id, anwser);
+            request.answer = anwser.clone();
+            Ok(Some(anwser))
+        }
+        // Shown in place of the reply too, button presses have no `on_error`.
+        Some(Err(error)) => {
+            debug!("{}: failed '{}': {}", request.channel_id, partial, error);
+            if !reply.content.trim().is_empty() {
+                reply.append(" ").await;
+            }
+            reply.append(&error.to_string()).await;
+            Err(error)
         }
     };
 
-    debug!("{}: prompt '{}'", ctx.channel_id(), &prompt);
+    if request.answer.trim().is_empty() {
+        reply.finish(Vec::new()).await;
+    } else {
+        if let Turn::Continue(previous) = &turn {
+            request.answer = format!("{} {}", previous.trim_end(), request.answer.trim_start());
+        }
+        if let Err(error) = remember(data, &request, &turn) {
+            error!("failed to record conversation: {error}");
+        }
+        reply.finish(reply_buttons()).await;
+        if let Some(message_id) = reply.target.message_id().await {
+            if let Err(error) = data.replies.record(message_id, &request) {
+                error!("failed to record reply: {error}");
+            }
+        }
+    }
+    anwser
+}
 
-    let new_msg = ctx.say("...").await.expect("");
+/// Puts the system prompt of the persona active in `channel_id` in front of
+/// `messages`.
+pub fn with_persona(
+    data: &Data,
+    guild_id: Option<GuildId>,
+    channel_id: ChannelId,
+    mut messages: Vec<Message>,
+) -> rusqlite::Result<Vec<Message>> {
+    if let Some(guild_id) = guild_id {
+        if let Some((_, system)) = data.personas.active(guild_id, channel_id)? {
+            messages.insert(0, Message::new(Role::System, system));
+        }
+    }
+    Ok(messages)
+}
 
-    let anwser = match prompt_ollama(prompt).await {
-        Ok(anwser) => anwser,
-        Err(error) => {
-            warn!("failed to get response: {}", error);
+/// Records the answer of `request` in the conversation of its channel.
+/// Regenerated and continued answers replace the answer they revise, or start
+/// a new conversation once that one is forgotten.
+fn remember(data: &Data, request: &Reply, turn: &Turn) -> rusqlite::Result<()> {
+    let config = data.config.get();
+    let timeout = config.ollama.context_timeout();
+    if let Turn::Regenerate(previous) | Turn::Continue(previous) = turn {
+        if data
+            .conversations
+            .revise(request.channel_id, previous, &request.answer, timeout)?
+        {
             return Ok(());
         }
+    }
+    data.conversations.record(
+        request.channel_id,
+        &request.prompt,
+        &request.answer,
+        timeout,
+        config.ollama.max_turns(),
+    )
+}
+
+/// Buttons under a finished answer.
+fn reply_buttons() -> Vec<CreateActionRow> {
+    vec![CreateActionRow::Buttons(vec![
+        CreateButton::new(REGENERATE_ID)
+            .label("Regenerate")
+            .style(ButtonStyle::Secondary),
+        CreateButton::new(CONTINUE_ID)
+            .label("Continue")
+            .style(ButtonStyle::Secondary),
+        CreateButton::new(GOOD_ID)
+            .emoji('👍')
+            .style(ButtonStyle::Secondary),
+        CreateButton::new(BAD_ID)
+            .emoji('👎')
+            .style(ButtonStyle::Secondary),
+    ])]
+}
+
+/// Handles presses of the buttons under finished answers, other component
+/// interactions are left alone.
+pub async fn reply_button(
+    ctx: &serenity::Context,
+    data: &Data,
+    press: &ComponentInteraction,
+) -> Result<(), Error> {
+    let custom_id = press.data.custom_id.as_str();
+    if ![REGENERATE_ID, CONTINUE_ID, GOOD_ID, BAD_ID].contains(&custom_id) {
+        return Ok(());
+    }
+    let ephemeral = |content: &str| {
+        CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new()
+                .content(content)
+                .ephemeral(true),
+        )
+    };
+
+    let rating = match custom_id {
+        GOOD_ID => Some(Rating::Up),
+        BAD_ID => Some(Rating::Down),
+        _ => None,
+    };
+    if let Some(rating) = rating {
+        let content = if data.replies.rate(press.message.id, press.user.id, rating)? {
+            debug!(
+                "{}: {} rated {}",
+                press.message.id,
+                press.user.id,
+                rating.as_str()
+            );
+            "thanks for the feedback"
+        } else {
+            FORGOTTEN
+        };
+        press.create_response(ctx, ephemeral(content)).await?;
+        return Ok(());
+    }
+
+    let Some(mut request) = data.replies.get(press.message.id)? else {
+        press.create_response(ctx, ephemeral(FORGOTTEN)).await?;
+        return Ok(());
     };
+    press
+        .create_response(ctx, CreateInteractionResponse::Acknowledge)
+        .await?;
 
-    debug!("{}: anwser '{}'", ctx.channel_id(), anwser);
+    let previous = std::mem::take(&mut request.answer);
+    let turn = if custom_id == REGENERATE_ID {
+        Turn::Regenerate(previous)
+    } else {
+        Turn::Continue(previous)
+    };
+    generate(Origin::Button(ctx, data, press), request, Vec::new(), turn).await?;
+    Ok(())
+}
 
-    let anwser_length = anwser.len();
-    if anwser_length > 2000 {
-        let mut anwsers: Vec<String> = Vec::new();
-        for i in (0..anwser_length).step_by(1000) {
-            anwsers.push(anwser[i..std::cmp::min(i + 1000, anwser_length)].to_string());
+/// Waits until `ticket` may start, keeping `target` updated with its
+/// position in line.
+async fn wait_in_line(target: &ReplyTarget<'_>, ticket: &Ticket, concurrency: usize) -> Permit {
+    let start = ticket.start(concurrency);
+    tokio::pin!(start);
+    let mut shown = None;
+    let permit = loop {
+        let position = ticket.position();
+        if let Some(position) = position.filter(|_| position != shown) {
+            shown = Some(position);
+            let content = format!("you are #{position} in line");
+            if let Err(error) = target.edit(content, None).await {
+                warn!("failed to show position in line: {error}");
+            }
         }
-        for message in anwsers {
-            debug!("{message}");
-            let builder = CreateMessage::new().content(message);
-            let _ = ctx.channel_id().send_message(ctx.http(), builder).await;
+        tokio::select! {
+            permit = &mut start => break permit,
+            _ = tokio::time::sleep(QUEUE_POLL) => {}
         }
-        return Ok(());
     };
+    if shown.is_some() {
+        let _ = target.edit(String::from("..."), None).await;
+    }
+    permit
+}
 
-    let builder = CreateReply::default().content(anwser.clone());
+/// Completes once `generation` is stopped with `chat stop` or by `author`
+/// pressing the button with `stop_id`.
+async fn stop_requested(
+    ctx: &serenity::Context,
+    generation: &GenerationHandle,
+    stop_id: &str,
+    author: UserId,
+) {
+    let pressed = async {
+        loop {
+            let id = stop_id.to_string();
+            let Some(press) = ComponentInteractionCollector::new(ctx)
+                .filter(move |press| press.data.custom_id == id)
+                .await
+            else {
+                return std::future::pending().await;
+            };
 
-    if let Err(error) = new_msg.edit(ctx, builder).await {
-        if error.to_string() == "Unknown Message" {
-            warn!("original message deleted sending new message");
-            ctx.say(anwser).await?;
+            if press.user.id == author {
+                if let Err(error) = press
+                    .create_response(ctx, CreateInteractionResponse::Acknowledge)
+                    .await
+                {
+                    error!("failed to answer stop button: {error}");
+                }
+                return;
+            }
+            let response = CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content("only the person who asked can stop this answer")
+                    .ephemeral(true),
+            );
+            if let Err(error) = press.create_response(ctx, response).await {
+                error!("failed to answer stop button: {error}");
+            }
         }
-        error!("Error sending message: {error:?}");
+    };
+    tokio::select! {
+        _ = generation.stopped() => {}
+        _ = pressed => {}
     }
-    status = OnlineStatus::Online;
-    activity = ActivityData::custom("");
-    ser_ctx.set_presence(Some(activity), status);
+}
+
+/// Message an answer is streamed into: the reply to a command, or a message
+/// sent to the channel of a pressed button.
+enum ReplyTarget<'a> {
+    Command(Context<'a>, ReplyHandle<'a>),
+    Channel(&'a serenity::Context, ChannelId, MessageId),
+}
+
+impl ReplyTarget<'_> {
+    /// Replaces the content of the message, and its components if given.
+    async fn edit(
+        &self,
+        content: String,
+        components: Option<Vec<CreateActionRow>>,
+    ) -> Result<(), serenity::Error> {
+        match self {
+            ReplyTarget::Command(ctx, handle) => {
+                let mut builder = CreateReply::default().content(content);
+                if let Some(components) = components {
+                    builder = builder.components(components);
+                }
+                handle.edit(*ctx, builder).await
+            }
+            ReplyTarget::Channel(ctx, channel_id, message_id) => {
+                let mut builder = EditMessage::new().content(content);
+                if let Some(components) = components {
+                    builder = builder.components(components);
+                }
+                channel_id.edit_message(ctx, *message_id, builder).await?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Sends a new message that further edits go to.
+    async fn follow_up(
+        &mut self,
+        content: String,
+        components: Vec<CreateActionRow>,
+    ) -> Result<(), serenity::Error> {
+        match self {
+            ReplyTarget::Command(ctx, handle) => {
+                let builder = CreateReply::default()
+                    .content(content)
+                    .components(components)
+                    .reply(true);
+                *handle = ctx.send(builder).await?;
+            }
+            ReplyTarget::Channel(ctx, channel_id, message_id) => {
+                let builder = CreateMessage::new().content(content).components(components);
+                *message_id = channel_id.send_message(*ctx, builder).await?.id;
+            }
+        }
+        Ok(())
+    }
+
+    async fn message_id(&self) -> Option<MessageId> {
+        match self {
+            ReplyTarget::Command(_, handle) => match handle.message().await {
+                Ok(message) => Some(message.id),
+                Err(error) => {
+                    error!("failed to get reply message: {error}");
+                    None
+                }
+            },
+            ReplyTarget::Channel(_, _, message_id) => Some(*message_id),
+        }
+    }
+}
+
+/// Reply that is progressively edited while the answer is streamed, moving
+/// on to follow-up messages whenever Discord's message limit is reached. The
+/// `components` stay on the latest message until the reply is finished.
+struct StreamedReply<'a> {
+    target: ReplyTarget<'a>,
+    content: String,
+    components: Vec<CreateActionRow>,
+    pending: usize,
+    last_edit: Instant,
+}
+
+impl<'a> StreamedReply<'a> {
+    fn new(target: ReplyTarget<'a>, components: Vec<CreateActionRow>) -> Self {
+        StreamedReply {
+            target,
+            content: String::new(),
+            components,
+            pending: 0,
+            last_edit: Instant::now(),
+        }
+    }
+
+    /// Adds `token` to the reply, finishing the current message and sending
+    /// a follow-up once the content no longer fits.
+    async fn append(&mut self, token: &str) {
+        self.content.push_str(token);
+        self.pending += 1;
+
+        if self.content.chars().count() <= MESSAGE_LIMIT {
+            return;
+        }
+
+        let mut chunks = split_message(&self.content, MESSAGE_LIMIT).into_iter();
+        self.content = chunks.next().unwrap_or_default();
+        let components = std::mem::take(&mut self.components);
+        self.edit().await;
+        self.components = components;
+        for chunk in chunks {
+            self.content = chunk;
+            if let Err(error) = self
+                .target
+                .follow_up(self.content.clone(), self.components.clone())
+                .await
+            {
+                error!("failed to send follow-up message: {error}");
+            }
+        }
+    }
+
+    /// Edits the message if enough tokens arrived or enough time passed.
+    async fn update(&mut self) {
+        if self.pending >= EDIT_TOKENS || self.last_edit.elapsed() >= EDIT_INTERVAL {
+            self.edit().await;
+        }
+    }
+
+    /// Edits the message one last time, replacing its components.
+    async fn finish(&mut self, components: Vec<CreateActionRow>) {
+        self.components = components;
+        self.edit().await;
+    }
+
+    async fn edit(&mut self) {
+        self.pending = 0;
+        self.last_edit = Instant::now();
+        if self.content.trim().is_empty() {
+            return;
+        }
+
+        let result = self
+            .target
+            .edit(self.content.clone(), Some(self.components.clone()))
+            .await;
+        if let Err(error) = result {
+            if error.to_string() == "Unknown Message" {
+                warn!("original message deleted sending new message");
+                if let Err(error) = self
+                    .target
+                    .follow_up(self.content.clone(), self.components.clone())
+                    .await
+                {
+                    error!("Error sending message: {error:?}");
+                }
+            } else {
+                error!("Error sending message: {error:?}");
+            }
+        }
+    }
+}
+
+// Kept apart from `llm`, whose prompt may start with any of these words.
+#[poise::command(
+    prefix_command,
+    slash_command,
+    subcommands("chat_reset", "chat_history", "chat_stop", "chat_feedback"),
+    subcommand_required,
+    help_text_fn = chat_help
+)]
+pub async fn chat(_ctx: Context<'_>) -> Result<(), Error> {
     Ok(())
 }
 
-pub fn llm_help() -> String {
-    String::from("queries offline local Ollama instance")
+pub fn chat_help() -> String {
+    String::from("manages the conversation llm has in this channel")
 }
 
-pub async fn prompt_ollama(prompt: String) -> Result<String, Error> {
-    let model = env::var("OLLAMA_MODEL").expect("'OLLAMA_MODEL' environment variable not set");
+#[poise::command(
+    prefix_command,
+    slash_command,
+    rename = "reset",
+    aliases("forget", "clear"),
+    help_text_fn = chat_reset_help
+)]
+pub async fn chat_reset(ctx: Context<'_>) -> Result<(), Error> {
+    let timeout = ctx.data().config.get().ollama.context_timeout();
+    if ctx.data().conversations.reset(ctx.channel_id(), timeout)? {
+        debug!("{}: conversation reset", ctx.channel_id());
+        ctx.say("conversation forgotten").await?;
+    } else {
+        ctx.say("no conversation to forget").await?;
+    }
+    Ok(())
+}
 
-    let post_url = env::var("OLLAMA_POST_URL").expect("'OLLAMA_IP' environment variable not set");
+pub fn chat_reset_help() -> String {
+    String::from("forgets the conversation in this channel")
+}
 
-    let client: Client = Client::new();
+#[poise::command(
+    prefix_command,
+    slash_command,
+    rename = "history",
+    help_text_fn = chat_history_help
+)]
+pub async fn chat_history(ctx: Context<'_>) -> Result<(), Error> {
+    let timeout = ctx.data().config.get().ollama.context_timeout();
+    let messages = ctx
+        .data()
+        .conversations
+        .history(ctx.channel_id(), timeout)?;
+    if messages.is_empty() {
+        ctx.say("no conversation in this channel").await?;
+        return Ok(());
+    }
 
-    let model = match json_strip_escape(&model) {
-        Ok(model) => model,
-        Err(error) => return Ok(error.to_string()),
-    };
-    let prompt_fmt = match json_strip_escape(&prompt) {
-        Ok(prompt) => prompt,
-        Err(error) => return Ok(error.to_string()),
+    let mut history = String::new();
+    for message in messages.iter().rev() {
+        let line = match message.role {
+            Role::User => format!(
+                "**>** {}\n",
+                truncate(&message.content.replace('\n', " "), 80)
+            ),
+            _ => format!("{}\n", truncate(&message.content.replace('\n', " "), 120)),
+        };
+        if history.chars().count() + line.chars().count() > 2000 {
+            break;
+        }
+        history.insert_str(0, &line);
+    }
+
+    ctx.say(history).await?;
+    Ok(())
+}
+
+pub fn chat_history_help() -> String {
+    String::from("shows the conversation in this channel")
+}
+
+#[poise::command(
+    prefix_command,
+    slash_command,
+    rename = "stop",
+    aliases("cancel"),
+    help_text_fn = chat_stop_help
+)]
+pub async fn chat_stop(ctx: Context<'_>) -> Result<(), Error> {
+    if ctx
+        .data()
+        .generations
+        .stop_latest(ctx.channel_id(), ctx.author().id)
+    {
+        debug!(
+            "{}: {} stopped an answer",
+            ctx.channel_id(),
+            ctx.author().id
+        );
+        ctx.say("stopped").await?;
+    } else {
+        ctx.say("you have no answer being written in this channel")
+            .await?;
+    }
+    Ok(())
+}
+
+pub fn chat_stop_help() -> String {
+    String::from("stops your answer that is being written in this channel")
+}
+
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only = true,
+    rename = "feedback",
+    required_permissions = "MANAGE_GUILD",
+    help_text_fn = chat_feedback_help
+)]
+pub async fn chat_feedback(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(NooqieError::NotInGuild)?;
+    let feedback = ctx.data().replies.feedback(guild_id)?;
+    if feedback.is_empty() {
+        ctx.say("no answers were rated in this server").await?;
+        return Ok(());
+    }
+
+    let mut lines = String::new();
+    for entry in &feedback {
+        lines.push_str(&serde_json::to_string(entry)?);
+        lines.push('\n');
+    }
+    let attachment = CreateAttachment::bytes(lines.into_bytes(), "feedback.jsonl");
+    ctx.send(
+        CreateReply::default()
+            .content(format!("{} rated answers", feedback.len()))
+            .attachment(attachment),
+    )
+    .await?;
+    Ok(())
+}
+
+pub fn chat_feedback_help() -> String {
+    String::from("exports the rated answers of this server as JSON lines")
+}
+
+/// Builds chat history from the messages the invoking message replies to,
+/// oldest first. Messages sent by the bot become assistant messages.
+async fn reply_chain(ctx: Context<'_>) -> Vec<Message> {
+    let poise::Context::Prefix(prefix_ctx) = ctx else {
+        return Vec::new();
     };
+    let bot_id = ctx.framework().bot_id;
 
-    let response = client
-        .post(post_url)
-        .body(format!(
-            r##"{{"model": "{model}", "prompt": "{prompt_fmt}", "stream": false }}"##,
-            model = model,
-            prompt_fmt = prompt_fmt
-        ))
-        .send()
-        .await;
-
-    match response {
-        Ok(t) => {
-            let response_text = t
-                .text()
-                .await
-                .expect("Invalid response could not parse to text");
-            let air: OllamaResponse = serde_json::from_str(&response_text)?;
-            Ok(air.response)
+    let mut messages: Vec<Message> = Vec::new();
+    let mut referenced = prefix_ctx.msg.referenced_message.clone().map(|msg| *msg);
+    let mut reference: Option<MessageReference> = prefix_ctx.msg.message_reference.clone();
+
+    while messages.len() < MAX_REPLY_CHAIN {
+        let msg = match (referenced.take(), reference.take()) {
+            (Some(msg), _) => msg,
+            (
+                None,
+                Some(MessageReference {
+                    message_id: Some(message_id),
+                    channel_id,
+                    ..
+                }),
+            ) => match channel_id.message(ctx, message_id).await {
+                Ok(msg) => msg,
+                Err(error) => {
+                    warn!("failed to fetch replied message: {error}");
+                    break;
+                }
+            },
+            _ => break,
+        };
+
+        if msg.author.id == bot_id {
+            messages.push(Message::new(Role::Assistant, msg.content.as_str()));
+        } else {
+            messages.push(Message::new(
+                Role::User,
+                strip_command(&msg.content, ctx.prefix()),
+            ));
         }
-        Err(error) => {
-            error!("failed to connect to Ollama server: {error}");
-            Ok(String::from("I seem to have dropped my brain :brain:"))
+        reference = msg.message_reference;
+    }
+
+    messages.reverse();
+    messages
+}
+
+/// Downloads the images attached to the invoking message and the message it
+/// replies to, plus `image` given to `llm`, base64 encoded.
+async fn images(ctx: Context<'_>, image: Option<Attachment>) -> Result<Vec<String>, Error> {
+    let mut attachments: Vec<Attachment> = image.into_iter().collect();
+    if let poise::Context::Prefix(prefix_ctx) = ctx {
+        attachments.extend(prefix_ctx.msg.attachments.iter().cloned());
+        let referenced = match (
+            &prefix_ctx.msg.referenced_message,
+            &prefix_ctx.msg.message_reference,
+        ) {
+            (Some(msg), _) => Some((**msg).clone()),
+            (
+                None,
+                Some(MessageReference {
+                    message_id: Some(message_id),
+                    channel_id,
+                    ..
+                }),
+            ) => channel_id.message(ctx, *message_id).await.ok(),
+            _ => None,
+        };
+        if let Some(msg) = referenced {
+            attachments.extend(msg.attachments);
+        }
+    }
+
+    let mut images = Vec::new();
+    let mut seen = Vec::new();
+    for attachment in attachments {
+        if !is_image(attachment.content_type.as_deref()) || seen.contains(&attachment.id) {
+            continue;
+        }
+        seen.push(attachment.id);
+        if images.len() == MAX_IMAGES {
+            return Err(NooqieError::InvalidArgument(format!(
+                "I can only look at {MAX_IMAGES} images at once"
+            )));
+        }
+        if attachment.size > MAX_IMAGE_SIZE {
+            return Err(NooqieError::InvalidArgument(format!(
+                "{} is too large to look at",
+                attachment.filename
+            )));
+        }
+        images.push(STANDARD.encode(attachment.download().await?));
+    }
+    Ok(images)
+}
+
+/// Whether an attachment of `content_type` is an image Ollama can decode.
+pub fn is_image(content_type: Option<&str>) -> bool {
+    matches!(
+        content_type.and_then(|content_type| content_type.split(';').next()),
+        Some("image/png" | "image/jpeg" | "image/webp")
+    )
+}
+
+/// Removes the prefix and command name from a message invoking a command.
+fn strip_command(content: &str, prefix: &str) -> String {
+    match content.strip_prefix(prefix) {
+        Some(invocation) => match invocation.trim_start().split_once(char::is_whitespace) {
+            Some((_command, args)) => args.trim().to_string(),
+            None => String::new(),
+        },
+        None => content.to_string(),
+    }
+}
+
+/// Inference server `llm` and friends talk to.
+#[async_trait]
+pub trait LlmBackend: Send + Sync {
+    /// Sends the chat `messages` to `model` and streams the reply of the
+    /// model, every token is passed to `tokens` as it arrives. A `seed` makes
+    /// the reply reproducible. Returns the whole reply.
+    async fn chat(
+        &self,
+        model: String,
+        messages: Vec<Message>,
+        seed: Option<u32>,
+        tokens: UnboundedSender<String>,
+    ) -> Result<String, Error>;
+
+    /// Lists the models available on the server.
+    async fn list_models(&self) -> Result<Vec<ModelTag>, Error>;
+
+    /// Shows the details and parameters of `model`, `UnknownModel` when the
+    /// server doesn't have it.
+    async fn show_model(&self, model: String) -> Result<ShowResponse, Error>;
+
+    /// Whether `model` can be sent images along with a message.
+    async fn supports_vision(&self, model: String) -> Result<bool, Error> {
+        Ok(self.show_model(model).await?.supports_vision())
+    }
+}
+
+/// Fails with `NoVision` when `images` are given but `model` can't see them.
+pub async fn check_vision(
+    backend: &dyn LlmBackend,
+    model: &str,
+    images: &[String],
+) -> Result<(), Error> {
+    if !images.is_empty() && !backend.supports_vision(model.to_string()).await? {
+        return Err(NooqieError::NoVision(model.to_string()));
+    }
+    Ok(())
+}
+
+/// Backend for the server described by `config`, sending its requests
+/// with `http`.
+pub fn backend(config: &BackendConfig, http: Client) -> Box<dyn LlmBackend> {
+    let url = config.url.trim_end_matches('/').to_string();
+    match config.api {
+        Api::Ollama => Box::new(Ollama { url, http }),
+        Api::OpenAi => Box::new(OpenAi {
+            url,
+            api_key: config.api_key.clone(),
+            vision: config.vision,
+            http,
+        }),
+    }
+}
+
+/// Backend used in the guild `ctx` was invoked in.
+pub fn guild_backend(ctx: Context<'_>) -> Box<dyn LlmBackend> {
+    backend(
+        &ctx.data().config.get().backend(ctx.guild_id()),
+        ctx.data().http.clone(),
+    )
+}
+
+/// Splits the complete lines off `buffer`, skipping blank ones.
+fn take_lines(buffer: &mut Vec<u8>) -> Vec<Vec<u8>> {
+    let mut lines = Vec::new();
+    while let Some(newline) = buffer.iter().position(|byte| *byte == b'\n') {
+        let line: Vec<u8> = buffer.drain(..=newline).collect();
+        if !line.iter().all(u8::is_ascii_whitespace) {
+            lines.push(line);
         }
     }
+    lines
+}
+
+/// Ollama's own API.
+pub struct Ollama {
+    url: String,
+    http: Client,
 }
 
-pub fn json_strip_escape(string: &str) -> std::result::Result<String, regex::Error> {
-    let expressions: Vec<(&str, &str)> = vec![
-        (r#"\n"#, r#" "#),  // linefeed
-        (r#"\\"#, r#"\\"#), // reverse solidus
-        (r#"/"#, r#"\/"#),  // solidus
-        (r#"""#, r#"\""#),  // quotation mark
-    ];
-    for exp in expressions {
-        debug!("{}", &string);
-        let s = match Regex::new(exp.0) {
-            Ok(re) => re,
-            Err(error) => {
-                debug!("{}", error);
-                return Err(error);
+impl Ollama {
+    fn endpoint(&self, endpoint: &str) -> String {
+        format!("{}/api/{endpoint}", self.url)
+    }
+}
+
+#[async_trait]
+impl LlmBackend for Ollama {
+    async fn chat(
+        &self,
+        model: String,
+        messages: Vec<Message>,
+        seed: Option<u32>,
+        tokens: UnboundedSender<String>,
+    ) -> Result<String, Error> {
+        let request = ChatRequest {
+            model,
+            messages,
+            stream: true,
+            options: seed.map(|seed| ChatOptions { seed }),
+        };
+
+        let mut response = self
+            .http
+            .post(self.endpoint("chat"))
+            .json(&request)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        let mut anwser = String::new();
+        let mut buffer: Vec<u8> = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            buffer.extend_from_slice(&chunk);
+            for line in take_lines(&mut buffer) {
+                match serde_json::from_slice(&line)? {
+                    ChatChunk::Response(part) => {
+                        anwser.push_str(&part.message.content);
+                        let _ = tokens.send(part.message.content);
+                        if part.done {
+                            return Ok(anwser);
+                        }
+                    }
+                    ChatChunk::Error { error } => return Err(NooqieError::Ollama(error)),
+                }
             }
+        }
+
+        Ok(anwser)
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelTag>, Error> {
+        let response: TagsResponse = self
+            .http
+            .get(self.endpoint("tags"))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(response.models)
+    }
+
+    async fn show_model(&self, model: String) -> Result<ShowResponse, Error> {
+        let response = self
+            .http
+            .post(self.endpoint("show"))
+            .json(&ShowRequest {
+                model: model.clone(),
+            })
+            .send()
+            .await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Err(NooqieError::UnknownModel(model));
+        }
+        Ok(response.error_for_status()?.json().await?)
+    }
+}
+
+/// A single server-sent event of a streamed `/v1/chat/completions` reply.
+#[derive(Deserialize)]
+pub struct CompletionChunk {
+    #[serde(default)]
+    pub choices: Vec<CompletionChoice>,
+}
+
+#[derive(Deserialize)]
+pub struct CompletionChoice {
+    #[serde(default)]
+    pub delta: CompletionDelta,
+    #[serde(default)]
+    pub finish_reason: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+pub struct CompletionDelta {
+    #[serde(default)]
+    pub content: Option<String>,
+}
+
+/// Body of a `/v1/chat/completions` request, which takes images as content
+/// parts instead of Ollama's `images`.
+#[derive(Serialize)]
+pub struct CompletionRequest {
+    pub model: String,
+    pub messages: Vec<CompletionMessage>,
+    pub stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u32>,
+}
+
+#[derive(Serialize)]
+pub struct CompletionMessage {
+    pub role: Role,
+    pub content: CompletionContent,
+}
+
+/// Plain text, or text followed by images for messages that have any.
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum CompletionContent {
+    Text(String),
+    Parts(Vec<ContentPart>),
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentPart {
+    Text { text: String },
+    ImageUrl { image_url: ImageUrl },
+}
+
+#[derive(Serialize)]
+pub struct ImageUrl {
+    pub url: String,
+}
+
+impl From<Message> for CompletionMessage {
+    fn from(message: Message) -> Self {
+        let content = if message.images.is_empty() {
+            CompletionContent::Text(message.content)
+        } else {
+            let mut parts = vec![ContentPart::Text {
+                text: message.content,
+            }];
+            parts.extend(
+                message
+                    .images
+                    .into_iter()
+                    .map(|image| ContentPart::ImageUrl {
+                        image_url: ImageUrl {
+                            url: format!("data:{};base64,{image}", image_mime(&image)),
+                        },
+                    }),
+            );
+            CompletionContent::Parts(parts)
         };
-        let string = s.replace_all(&string, exp.1);
-        debug!("{}", &string);
-    }
-    Ok(String::from(string))
-    // let re_backspace = Regex::new(r#"\b"#).unwrap();
-    // let re_formfeed = Regex::new(r#"\f"#).unwrap();
-    // let re_carriage_return = Regex::new(r#"\r"#).unwrap();
-    // let re_horizontal_tab = Regex::new(r#"\t"#).unwrap();
-    // let re_hex = Regex::new(r#"/\u[a-fA-F0-9]{8}"#).unwrap();
-
-    // let string = re_backspace.replace_all(&string, r#" "#);
-    // let string = re_formfeed.replace_all(&string, r#" "#);
-    // let string = re_carriage_return.replace_all(&string, r#" "#);
-    // let string = re_horizontal_tab.replace_all(&string, r#"    "#);
+        CompletionMessage {
+            role: message.role,
+            content,
+        }
+    }
+}
+
+/// MIME type of a base64 encoded image, told apart by the encoded magic
+/// bytes of the formats `is_image` lets through.
+fn image_mime(image: &str) -> &'static str {
+    if image.starts_with("/9j/") {
+        "image/jpeg"
+    } else if image.starts_with("UklGR") {
+        "image/webp"
+    } else {
+        "image/png"
+    }
+}
+
+#[derive(Deserialize)]
+pub struct OpenAiModels {
+    pub data: Vec<OpenAiModel>,
+}
+
+#[derive(Deserialize)]
+pub struct OpenAiModel {
+    pub id: String,
+    #[serde(default)]
+    pub owned_by: String,
+}
+
+/// Servers speaking the OpenAI API, like vLLM or llama.cpp's server.
+pub struct OpenAi {
+    url: String,
+    api_key: Option<String>,
+    vision: bool,
+    http: Client,
+}
+
+impl OpenAi {
+    fn request(&self, method: reqwest::Method, endpoint: &str) -> reqwest::RequestBuilder {
+        let request = self
+            .http
+            .request(method, format!("{}/{endpoint}", self.url));
+        match &self.api_key {
+            Some(api_key) => request.bearer_auth(api_key),
+            None => request,
+        }
+    }
+
+    async fn models(&self) -> Result<Vec<OpenAiModel>, Error> {
+        let response: OpenAiModels = self
+            .request(reqwest::Method::GET, "models")
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(response.data)
+    }
+}
+
+#[async_trait]
+impl LlmBackend for OpenAi {
+    async fn chat(
+        &self,
+        model: String,
+        messages: Vec<Message>,
+        seed: Option<u32>,
+        tokens: UnboundedSender<String>,
+    ) -> Result<String, Error> {
+        let request = CompletionRequest {
+            model,
+            messages: messages.into_iter().map(CompletionMessage::from).collect(),
+            stream: true,
+            seed,
+        };
+
+        let mut response = self
+            .request(reqwest::Method::POST, "chat/completions")
+            .json(&request)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        let mut anwser = String::new();
+        let mut buffer: Vec<u8> = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            buffer.extend_from_slice(&chunk);
+            for line in take_lines(&mut buffer) {
+                let line = String::from_utf8_lossy(&line);
+                let Some(data) = line.trim().strip_prefix("data:") else {
+                    continue;
+                };
+                let data = data.trim();
+                if data == "[DONE]" {
+                    return Ok(anwser);
+                }
+                let chunk: CompletionChunk = serde_json::from_str(data)?;
+                for choice in chunk.choices {
+                    if let Some(content) = choice.delta.content {
+                        anwser.push_str(&content);
+                        let _ = tokens.send(content);
+                    }
+                    if choice.finish_reason.is_some() {
+                        return Ok(anwser);
+                    }
+                }
+            }
+        }
+
+        Ok(anwser)
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelTag>, Error> {
+        Ok(self
+            .models()
+            .await?
+            .into_iter()
+            .map(|model| ModelTag {
+                name: model.id,
+                size: 0,
+                details: ModelDetails::default(),
+            })
+            .collect())
+    }
+
+    /// Configured, the servers don't say which models see images.
+    async fn supports_vision(&self, _model: String) -> Result<bool, Error> {
+        Ok(self.vision)
+    }
+
+    /// Taken from the list of models, servers like vLLM and llama.cpp don't
+    /// serve single ones.
+    async fn show_model(&self, model: String) -> Result<ShowResponse, Error> {
+        let model = self
+            .models()
+            .await?
+            .into_iter()
+            .find(|info| info.id == model)
+            .ok_or(NooqieError::UnknownModel(model))?;
+        Ok(ShowResponse {
+            parameters: String::new(),
+            template: String::new(),
+            details: ModelDetails {
+                family: model.owned_by,
+                ..Default::default()
+            },
+            capabilities: Vec::new(),
+        })
+    }
 }
@@ -0,0 +1,186 @@
+use log::debug;
+
+use poise::serenity_prelude::GuildChannel;
+
+use crate::{Context, Error, NooqieError};
+
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only = true,
+    subcommands("persona_set", "persona_list", "persona_use", "persona_delete"),
+    subcommand_required,
+    help_text_fn = persona_help
+)]
+pub async fn persona(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+pub fn persona_help() -> String {
+    String::from("manages the system prompts given to the LLM")
+}
+
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only = true,
+    rename = "set",
+    required_permissions = "MANAGE_GUILD",
+    help_text_fn = persona_set_help
+)]
+pub async fn persona_set(
+    ctx: Context<'_>,
+    #[description = "Persona name"] name: String,
+    #[description = "System prompt"]
+    #[rest]
+    prompt: String,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(NooqieError::NotInGuild)?;
+    ctx.data().personas.set(guild_id, &name, &prompt)?;
+    debug!("{}: persona '{}' set", guild_id, name);
+    ctx.say(format!("persona `{}` saved", name.to_lowercase()))
+        .await?;
+    Ok(())
+}
+
+pub fn persona_set_help() -> String {
+    String::from("creates or replaces a persona")
+}
+
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only = true,
+    rename = "list",
+    help_text_fn = persona_list_help
+)]
+pub async fn persona_list(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(NooqieError::NotInGuild)?;
+    let personas = ctx.data().personas.list(guild_id)?;
+    if personas.is_empty() {
+        ctx.say("no personas, create one with `persona set`")
+            .await?;
+        return Ok(());
+    }
+
+    let active = ctx
+        .data()
+        .personas
+        .active(guild_id, ctx.channel_id())?
+        .map(|(name, _)| name);
+
+    let mut list = String::new();
+    for (name, prompt) in personas {
+        let marker = if active.as_ref() == Some(&name) {
+            " (active)"
+        } else {
+            ""
+        };
+        let prompt: String = prompt.chars().take(100).collect();
+        let line = format!("**{name}**{marker}: {prompt}\n");
+        if list.chars().count() + line.chars().count() > 2000 {
+            break;
+        }
+        list.push_str(&line);
+    }
+
+    ctx.say(list).await?;
+    Ok(())
+}
+
+pub fn persona_list_help() -> String {
+    String::from("lists the personas of this server")
+}
+
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only = true,
+    rename = "use",
+    required_permissions = "MANAGE_GUILD",
+    help_text_fn = persona_use_help
+)]
+pub async fn persona_use(
+    ctx: Context<'_>,
+    #[description = "Persona name, or \"none\""] name: String,
+    #[description = "Only use the persona in this channel"] channel: Option<GuildChannel>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(NooqieError::NotInGuild)?;
+    if channel
+        .as_ref()
+        .is_some_and(|channel| channel.guild_id != guild_id)
+    {
+        return Err(NooqieError::InvalidArgument(String::from(
+            "that channel is not in this server",
+        )));
+    }
+    let channel_id = channel.map(|channel| channel.id);
+    let persona = match name.to_lowercase().as_str() {
+        "none" => None,
+        _ => Some(name.as_str()),
+    };
+
+    if !ctx
+        .data()
+        .personas
+        .activate(guild_id, channel_id, persona)?
+    {
+        ctx.say(format!("no persona called `{name}`")).await?;
+        return Ok(());
+    }
+
+    let scope = match channel_id {
+        Some(channel_id) => format!("<#{channel_id}>"),
+        None => String::from("this server"),
+    };
+    match persona {
+        Some(persona) => {
+            debug!("{}: persona '{}' active in {}", guild_id, persona, scope);
+            ctx.say(format!(
+                "using persona `{}` in {scope}",
+                persona.to_lowercase()
+            ))
+            .await?;
+        }
+        None if channel_id.is_some() => {
+            ctx.say(format!("{scope} uses the persona of this server"))
+                .await?;
+        }
+        None => {
+            ctx.say(format!("no persona in {scope}")).await?;
+        }
+    }
+    Ok(())
+}
+
+pub fn persona_use_help() -> String {
+    String::from("activates a persona for this server or a single channel")
+}
+
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only = true,
+    rename = "delete",
+    aliases("remove"),
+    required_permissions = "MANAGE_GUILD",
+    help_text_fn = persona_delete_help
+)]
+pub async fn persona_delete(
+    ctx: Context<'_>,
+    #[description = "Persona name"] name: String,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(NooqieError::NotInGuild)?;
+    if ctx.data().personas.delete(guild_id, &name)? {
+        debug!("{}: persona '{}' deleted", guild_id, name);
+        ctx.say(format!("persona `{}` deleted", name.to_lowercase()))
+            .await?;
+    } else {
+        ctx.say(format!("no persona called `{name}`")).await?;
+    }
+    Ok(())
+}
+
+pub fn persona_delete_help() -> String {
+    String::from("deletes a persona")
+}
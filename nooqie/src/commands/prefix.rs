@@ -0,0 +1,77 @@
+use log::debug;
+
+use crate::{Context, Error, NooqieError};
+
+/// Longest prefix that can be set, so it stays easy to type.
+const MAX_PREFIX_LENGTH: usize = 8;
+
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only = true,
+    subcommands("prefix_set", "prefix_reset"),
+    subcommand_required,
+    help_text_fn = prefix_help
+)]
+pub async fn prefix(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+pub fn prefix_help() -> String {
+    String::from("changes the command prefix of this server, mentioning me always works")
+}
+
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only = true,
+    rename = "set",
+    required_permissions = "MANAGE_GUILD",
+    help_text_fn = prefix_set_help
+)]
+pub async fn prefix_set(
+    ctx: Context<'_>,
+    #[description = "New prefix"] prefix: String,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(NooqieError::NotInGuild)?;
+    if prefix.chars().any(char::is_whitespace) {
+        return Err(NooqieError::InvalidArgument(String::from(
+            "the prefix can't contain spaces",
+        )));
+    }
+    if prefix.chars().count() > MAX_PREFIX_LENGTH {
+        return Err(NooqieError::InvalidArgument(format!(
+            "the prefix can't be longer than {MAX_PREFIX_LENGTH} characters"
+        )));
+    }
+
+    ctx.data().prefixes.set(guild_id, Some(&prefix))?;
+    debug!("{}: prefix set to '{}'", guild_id, prefix);
+    ctx.say(format!("prefix set to `{prefix}`")).await?;
+    Ok(())
+}
+
+pub fn prefix_set_help() -> String {
+    String::from("sets the command prefix of this server")
+}
+
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only = true,
+    rename = "reset",
+    required_permissions = "MANAGE_GUILD",
+    help_text_fn = prefix_reset_help
+)]
+pub async fn prefix_reset(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(NooqieError::NotInGuild)?;
+    ctx.data().prefixes.set(guild_id, None)?;
+    debug!("{}: prefix reset", guild_id);
+    let prefix = ctx.data().config.get().bot.prefix.clone();
+    ctx.say(format!("prefix reset to `{prefix}`")).await?;
+    Ok(())
+}
+
+pub fn prefix_reset_help() -> String {
+    String::from("goes back to the default command prefix")
+}
@@ -1,5 +1,18 @@
 use crate::{Context, Error};
 
+use poise::{
+    serenity_prelude::{
+        Colour, ComponentInteractionCollector, CreateActionRow, CreateButton, CreateEmbed,
+        CreateEmbedFooter, CreateInteractionResponse, CreateInteractionResponseMessage,
+    },
+    CreateReply,
+};
+
+use std::time::Duration;
+
+/// How long page buttons keep working after the last press.
+const PAGINATION_TIMEOUT: Duration = Duration::from_secs(300);
+
 #[poise::command(
     prefix_command,
     track_edits,
@@ -59,3 +72,76 @@ pub async fn help(
     .await?;
     Ok(())
 }
+
+fn page_embed(title: &str, pages: &[String], page: usize) -> CreateEmbed {
+    let embed = CreateEmbed::new()
+        .title(title)
+        .description(&pages[page])
+        .colour(Colour::BLURPLE);
+    if pages.len() > 1 {
+        embed.footer(CreateEmbedFooter::new(format!(
+            "page {}/{}",
+            page + 1,
+            pages.len()
+        )))
+    } else {
+        embed
+    }
+}
+
+/// Sends `pages` as an embed with buttons to go back and forth between them,
+/// returns once the buttons have not been used for `PAGINATION_TIMEOUT`.
+pub async fn paginate(ctx: Context<'_>, title: &str, pages: &[String]) -> Result<(), Error> {
+    if pages.is_empty() {
+        return Ok(());
+    }
+
+    let prev_button_id = format!("{}prev", ctx.id());
+    let next_button_id = format!("{}next", ctx.id());
+    let mut reply = CreateReply::default().embed(page_embed(title, pages, 0));
+    if pages.len() > 1 {
+        reply = reply.components(vec![CreateActionRow::Buttons(vec![
+            CreateButton::new(&prev_button_id).emoji('◀'),
+            CreateButton::new(&next_button_id).emoji('▶'),
+        ])]);
+    }
+    let handle = ctx.send(reply).await?;
+    if pages.len() == 1 {
+        return Ok(());
+    }
+
+    let mut page = 0;
+    let ctx_id = ctx.id();
+    while let Some(press) = ComponentInteractionCollector::new(ctx)
+        .filter(move |press| press.data.custom_id.starts_with(&ctx_id.to_string()))
+        .timeout(PAGINATION_TIMEOUT)
+        .await
+    {
+        if press.data.custom_id == next_button_id {
+            page = (page + 1) % pages.len();
+        } else if press.data.custom_id == prev_button_id {
+            page = page.checked_sub(1).unwrap_or(pages.len() - 1);
+        } else {
+            continue;
+        }
+
+        press
+            .create_response(
+                ctx.serenity_context(),
+                CreateInteractionResponse::UpdateMessage(
+                    CreateInteractionResponseMessage::new().embed(page_embed(title, pages, page)),
+                ),
+            )
+            .await?;
+    }
+
+    handle
+        .edit(
+            ctx,
+            CreateReply::default()
+                .embed(page_embed(title, pages, page))
+                .components(Vec::new()),
+        )
+        .await?;
+    Ok(())
+}
@@ -1,35 +1,77 @@
-use crate::{Context, Error};
+use crate::commands::utils::paginate;
+use crate::tracks::{
+    format_duration, is_audio_file, move_entry, parse_timestamp, probe_duration, progress_bar,
+    queue_pages, shuffle_upcoming, sound_name, sounds as list_sounds, track_info, truncate,
+    url_file_name, FlatPlaylist, Source, TrackInfo, TrackInfoKey, AUDIO_EXTENSIONS,
+};
+use crate::{
+    config::SharedConfig, idle::IdleTimers, listen::Sessions, presence::Presence, Context, Data,
+    Error, NooqieError,
+};
 
 use poise::{
     async_trait,
-    serenity_prelude::{prelude::TypeMapKey, ActivityData, ChannelId, GuildId, OnlineStatus},
+    serenity_prelude::{
+        self as serenity, prelude::TypeMapKey, ChannelId, Colour, ComponentInteractionCollector,
+        ComponentInteractionDataKind, CreateActionRow, CreateEmbed, CreateInteractionResponse,
+        CreateInteractionResponseMessage, CreateMessage, CreateSelectMenu, CreateSelectMenuKind,
+        CreateSelectMenuOption, GuildId,
+    },
+    CreateReply,
 };
 
 use log::{debug, error, info, warn};
 
 use songbird::{
     events::{Event, EventContext, EventHandler as VoiceEventHandler, TrackEvent},
-    input::YoutubeDl,
-    tracks::TrackHandle,
+    input::{AuxMetadata, Compose, File, HttpRequest, Input, YoutubeDl},
+    tracks::{PlayMode, Queued, Track, TrackHandle, TrackQueue},
     Songbird,
 };
 
-use std::sync::Arc;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 
 use reqwest::Client as HttpClient;
 
+use tokio::process::Command;
+
+/// Tracks shown per page of `queue`.
+const QUEUE_PAGE_SIZE: usize = 10;
+/// Sound names shown per page of `sounds`.
+const SOUNDS_PAGE_SIZE: usize = 50;
+/// Results offered when `play` is given search terms.
+const SEARCH_RESULTS: usize = 5;
+/// How long the requester has to pick a search result.
+const SEARCH_TIMEOUT: Duration = Duration::from_secs(60);
+/// Seconds skipped by `forward` and `rewind` when no amount is given.
+const DEFAULT_SKIP_SECONDS: u64 = 10;
+/// Loudest volume `volume` accepts, in percent.
+const MAX_VOLUME: u32 = 200;
+/// Segments of the progress bar shown by `nowplaying`.
+const PROGRESS_BAR_WIDTH: usize = 20;
+/// Longest title shown when a track starts playing.
+const MAX_EMBED_TITLE_LENGTH: usize = 200;
+/// Longest label or description of a select menu option allowed by Discord.
+const MAX_OPTION_LENGTH: usize = 100;
+
 pub struct HttpKey;
 
 impl TypeMapKey for HttpKey {
     type Value = HttpClient;
 }
 
-async fn get_voice_info(ctx: Context<'_>) -> Result<(GuildId, ChannelId), String> {
+pub async fn get_voice_info(ctx: Context<'_>) -> Result<(GuildId, ChannelId), Error> {
     let (guild_id, channel_id) = {
         let guild = match ctx.guild() {
             Some(guild) => guild,
             None => {
-                return Err(String::from("user not in guild"));
+                return Err(NooqieError::NotInGuild);
             }
         };
         let channel_id = guild
@@ -41,17 +83,18 @@ async fn get_voice_info(ctx: Context<'_>) -> Result<(GuildId, ChannelId), String
 
     match channel_id {
         Some(thing) => Ok((guild_id, thing)),
-        _ => Err(String::from("user not in voice channel, aborting")),
+        _ => Err(NooqieError::NotInVoiceChannel),
     }
 }
 
-async fn get_manager(ctx: Context<'_>) -> Result<Arc<Songbird>, String> {
+async fn get_manager(ctx: Context<'_>) -> Result<Arc<Songbird>, Error> {
     let manager = songbird::get(ctx.as_ref()).await;
     match manager {
         Some(manager) => Ok(manager.clone()),
-        _ => Err(String::from(
-            "Songbird Voice client placed in at initialisation.",
-        )),
+        _ => {
+            error!("Songbird Voice client not placed in at initialisation.");
+            Err(NooqieError::NoVoiceClient)
+        }
     }
 }
 
@@ -65,35 +108,21 @@ async fn get_manager(ctx: Context<'_>) -> Result<Arc<Songbird>, String> {
     help_text_fn = join_help
 )]
 pub async fn join(ctx: Context<'_>) -> Result<(), Error> {
-    let (guild_id, connect_to) = match get_voice_info(ctx).await {
-        Ok((guild_id, connect_to)) => (guild_id, connect_to),
-        Err(err) => {
-            error!("{err}");
-            return Ok(());
-        }
-    };
-
-    let manager = match get_manager(ctx).await {
-        Ok(manager) => manager,
-        Err(err) => {
-            error!("{err}");
-            return Ok(());
-        }
-    };
-
-    if let Ok(handler_lock) = manager.join(guild_id, connect_to).await {
-        let mut handler = handler_lock.lock().await;
-        let current_channel = match handler.current_channel() {
-            Some(channel) => channel.to_string(),
-            None => {
-                warn!("user not in voice channel, aborting");
-                return Ok(());
-            }
-        };
-        debug!("{}: joined channel", current_channel);
-        handler.add_global_event(TrackEvent::Error.into(), TrackErrorNotifier);
-    }
-
+    let (guild_id, connect_to) = get_voice_info(ctx).await?;
+
+    let manager = get_manager(ctx).await?;
+    join_voice(&manager, guild_id, connect_to).await?;
+
+    let timeout = ctx.data().config.get().voice.idle_timeout();
+    check_idle(
+        ctx.serenity_context(),
+        &ctx.data().idle,
+        &ctx.data().listening,
+        timeout,
+        guild_id,
+        None,
+    )
+    .await;
     Ok(())
 }
 
@@ -107,47 +136,25 @@ pub async fn join(ctx: Context<'_>) -> Result<(), Error> {
     help_text_fn = leave_help
 )]
 pub async fn leave(ctx: Context<'_>) -> Result<(), Error> {
-    let guild_id = match get_voice_info(ctx).await {
-        Ok(info) => info.0,
-        Err(err) => {
-            error!("{err}");
-            return Ok(());
-        }
-    };
-
-    let manager = match get_manager(ctx).await {
-        Ok(manager) => manager,
-        Err(err) => {
-            error!("{err}");
-            return Ok(());
-        }
-    };
+    let (guild_id, _) = get_voice_info(ctx).await?;
 
-    let mut current_channel = String::from("");
+    let manager = get_manager(ctx).await?;
 
-    if let Some(handler_lock) = manager.get(guild_id) {
-        let handler = handler_lock.lock().await;
-        current_channel = match handler.current_channel() {
-            Some(channel) => channel.to_string(),
-            None => {
-                warn!("user not in voice channel, aborting");
-                return Ok(());
+    let current_channel = match manager.get(guild_id) {
+        Some(handler_lock) => {
+            let handler = handler_lock.lock().await;
+            match handler.current_channel() {
+                Some(channel) => channel.to_string(),
+                None => return Err(NooqieError::NotConnected),
             }
-        };
-    } else {
-        warn!("can't leave not in voice channel, aborting");
-    }
-
-    let has_handler = manager.get(guild_id).is_some();
-
-    if has_handler {
-        if let Err(error) = manager.remove(guild_id).await {
-            error!("failed to disconnect: {:?}", error);
         }
-        debug!("{}: disconnected from voice channel", current_channel);
-    } else {
-        warn!("can't leave not in voice channel, aborting");
-    }
+        None => return Err(NooqieError::NotConnected),
+    };
+
+    manager.remove(guild_id).await?;
+    ctx.data().idle.forget(guild_id);
+    ctx.data().listening.stop(guild_id);
+    debug!("{}: disconnected from voice channel", current_channel);
 
     Ok(())
 }
@@ -170,6 +177,87 @@ impl VoiceEventHandler for TrackErrorNotifier {
     }
 }
 
+/// Announces a track in the channel it was requested in once it starts
+/// playing and shows it as the bot's presence, which stops showing the guild
+/// again when the track ends with nothing left in its queue.
+struct NowPlayingNotifier {
+    ctx: serenity::Context,
+    guild_id: GuildId,
+    queue: TrackQueue,
+    info: Arc<TrackInfo>,
+    announced: Arc<AtomicBool>,
+    idle: IdleTimers,
+    listening: Sessions,
+    presence: Presence,
+    config: SharedConfig,
+}
+
+fn now_playing_embed(info: &TrackInfo) -> CreateEmbed {
+    let duration = match info.duration {
+        Some(duration) => format_duration(duration),
+        None => String::from("live"),
+    };
+    let mut embed = CreateEmbed::new()
+        .title("Now playing")
+        .description(info.link(MAX_EMBED_TITLE_LENGTH))
+        .field("Duration", duration, true)
+        .field("Requested by", format!("<@{}>", info.requester), true)
+        .colour(Colour::BLURPLE);
+    if let Some(thumbnail) = &info.thumbnail {
+        embed = embed.thumbnail(thumbnail);
+    }
+    embed
+}
+
+#[async_trait]
+impl VoiceEventHandler for NowPlayingNotifier {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        let EventContext::Track(track_list) = ctx else {
+            return None;
+        };
+        for (state, handle) in *track_list {
+            match state.playing {
+                PlayMode::Play => {
+                    self.presence
+                        .playing(&self.ctx, self.guild_id, Some(self.info.title.clone()));
+                    if self.announced.swap(true, Ordering::Relaxed) {
+                        continue;
+                    }
+                    let message = CreateMessage::new().embed(now_playing_embed(&self.info));
+                    if let Err(error) = self
+                        .info
+                        .channel_id
+                        .send_message(&self.ctx.http, message)
+                        .await
+                    {
+                        error!("failed to announce track {:?}: {}", handle.uuid(), error);
+                    }
+                }
+                PlayMode::End | PlayMode::Stop | PlayMode::Errored(_) => {
+                    let uuid = handle.uuid();
+                    let remaining = self.queue.current_queue();
+                    if remaining.iter().all(|track| track.uuid() == uuid) {
+                        debug!("queue finished, clearing presence");
+                        self.presence.playing(&self.ctx, self.guild_id, None);
+                        let timeout = self.config.get().voice.idle_timeout();
+                        check_idle(
+                            &self.ctx,
+                            &self.idle,
+                            &self.listening,
+                            timeout,
+                            self.guild_id,
+                            Some(handle),
+                        )
+                        .await;
+                    }
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+}
+
 #[poise::command(
     prefix_command,
     track_edits,
@@ -181,79 +269,602 @@ impl VoiceEventHandler for TrackErrorNotifier {
 )]
 pub async fn play(
     ctx: Context<'_>,
-    #[description = "Youtube URL"] msg: Option<String>,
+    #[description = "Audio file to play"] file: Option<serenity::Attachment>,
+    #[description = "YouTube URL, audio file URL, sound name or search terms"]
+    #[rest]
+    msg: Option<String>,
 ) -> Result<(), Error> {
-    let (guild_id, connect_to) = match get_voice_info(ctx).await {
-        Ok((guild_id, connect_to)) => (guild_id, connect_to),
-        Err(err) => {
-            error!("{err}");
-            return Ok(());
+    let (guild_id, connect_to) = get_voice_info(ctx).await?;
+
+    let manager = get_manager(ctx).await?;
+
+    let http_client = http_client(ctx).await;
+
+    let track = match (file, msg) {
+        (Some(file), _) => attachment_track(ctx, http_client, file).await?,
+        (None, Some(query)) => {
+            let soundboard = ctx.data().config.get().voice.soundboard.clone();
+            match Source::of(&query, soundboard.as_deref()) {
+                Source::Playlist(url) => {
+                    let voice = (guild_id, connect_to);
+                    return play_playlist(ctx, &manager, voice, http_client, &url).await;
+                }
+                source => match resolve_track(ctx, http_client, source).await? {
+                    Some(track) => track,
+                    None => return Ok(()),
+                },
+            }
+        }
+        (None, None) => {
+            return Err(NooqieError::MissingArgument(
+                "YouTube URL, audio file or search terms",
+            ))
         }
     };
 
-    let manager = match get_manager(ctx).await {
-        Ok(manager) => manager,
-        Err(err) => {
-            error!("{err}");
-            return Ok(());
+    join_voice(&manager, guild_id, connect_to).await?;
+    enqueue(ctx, &manager, guild_id, vec![track], false).await?;
+    Ok(())
+}
+
+async fn http_client(ctx: Context<'_>) -> HttpClient {
+    let data = ctx.serenity_context().data.read().await;
+    data.get::<HttpKey>()
+        .cloned()
+        .expect("Guaranteed to exist in the typemap.")
+}
+
+/// Turns a source other than a playlist into a single track, `None` when
+/// the author didn't pick a search result.
+async fn resolve_track(
+    ctx: Context<'_>,
+    http_client: HttpClient,
+    source: Source,
+) -> Result<Option<(Input, TrackInfo)>, Error> {
+    let (requester, channel_id) = (ctx.author().id, ctx.channel_id());
+    let (src, metadata, url): (Input, _, _) = match source {
+        Source::Playlist(_) => {
+            return Err(NooqieError::InvalidArgument(String::from(
+                "use `play` to queue a playlist",
+            )))
+        }
+        Source::Audio(url) => {
+            let info = TrackInfo {
+                title: url_file_name(&url),
+                url: url.clone(),
+                duration: None,
+                thumbnail: None,
+                requester,
+                channel_id,
+            };
+            return Ok(Some((HttpRequest::new(http_client, url).into(), info)));
+        }
+        Source::Sound(path) => {
+            let probed = path.clone();
+            let duration = tokio::task::spawn_blocking(move || probe_duration(&probed))
+                .await
+                .ok()
+                .flatten();
+            let info = TrackInfo {
+                title: sound_name(&path),
+                url: sound_name(&path),
+                duration,
+                thumbnail: None,
+                requester,
+                channel_id,
+            };
+            return Ok(Some((File::new(path).into(), info)));
         }
+        Source::YtDlp(query) => {
+            let mut src = YoutubeDl::new(http_client, query.clone());
+            let metadata = match src.aux_metadata().await {
+                Ok(metadata) => metadata,
+                Err(error) => {
+                    warn!("failed to get metadata of '{}': {}", query, error);
+                    Default::default()
+                }
+            };
+            (src.into(), metadata, query)
+        }
+        Source::Search(query) => match pick_search_result(ctx, http_client, &query).await? {
+            Some((src, metadata)) => (src.into(), metadata, query),
+            None => return Ok(None),
+        },
     };
+    let url = metadata_url(&metadata, &url);
+    let info = TrackInfo::from_metadata(metadata, &url, requester, channel_id);
+    Ok(Some((src, info)))
+}
 
-    let url = match msg {
-        Some(msg) => msg,
-        None => {
-            warn!("missing YouTube URL, aborting");
-            return Ok(());
-        }
+/// Plays an audio file uploaded with the command straight from Discord.
+async fn attachment_track(
+    ctx: Context<'_>,
+    http_client: HttpClient,
+    file: serenity::Attachment,
+) -> Result<(Input, TrackInfo), Error> {
+    if !is_audio_file(&file.filename) {
+        return Err(NooqieError::InvalidArgument(format!(
+            "`{}` isn't an {} file",
+            file.filename,
+            AUDIO_EXTENSIONS.join(", ")
+        )));
+    }
+    let info = TrackInfo {
+        title: file.filename.clone(),
+        url: file.url.clone(),
+        duration: None,
+        thumbnail: None,
+        requester: ctx.author().id,
+        channel_id: ctx.channel_id(),
     };
+    Ok((HttpRequest::new(http_client, file.url).into(), info))
+}
 
-    if let Ok(handler_lock) = manager.join(guild_id, connect_to).await {
-        let mut handler = handler_lock.lock().await;
-        let current_channel = match handler.current_channel() {
-            Some(channel) => channel.to_string(),
-            None => {
-                warn!("user not in voice channel, aborting");
-                return Ok(());
-            }
-        };
+/// Queues the entries of the playlist at `url`, up to `voice.max_playlist`.
+async fn play_playlist(
+    ctx: Context<'_>,
+    manager: &Songbird,
+    (guild_id, connect_to): (GuildId, ChannelId),
+    http_client: HttpClient,
+    url: &str,
+) -> Result<(), Error> {
+    ctx.defer().await?;
+    let limit = ctx.data().config.get().voice.max_playlist;
+    let playlist = flat_playlist(url, limit).await?;
+    let tracks: Vec<_> = playlist
+        .entries
+        .iter()
+        .filter_map(|entry| {
+            let url = entry.url()?;
+            let info =
+                TrackInfo::from_metadata(entry.metadata(), &url, ctx.author().id, ctx.channel_id());
+            Some((YoutubeDl::new(http_client.clone(), url).into(), info))
+        })
+        .collect();
+    if tracks.is_empty() {
+        return Err(NooqieError::InvalidArgument(String::from(
+            "that playlist is empty",
+        )));
+    }
+
+    join_voice(manager, guild_id, connect_to).await?;
+    let total = tracks.len();
+    let queued = enqueue(ctx, manager, guild_id, tracks, false).await?;
+    let mut summary = match playlist.title {
+        Some(title) => format!("queued {queued} tracks from `{title}`"),
+        None => format!("queued {queued} tracks"),
+    };
+    if queued < total {
+        summary.push_str(&format!(
+            ", {} skipped because the queue is full",
+            total - queued
+        ));
+    }
+    ctx.say(summary).await?;
+    Ok(())
+}
+
+/// Joins `channel_id`, or moves there when already connected elsewhere.
+pub async fn join_voice(
+    manager: &Songbird,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+) -> Result<(), Error> {
+    // Global events outlive moves between channels, so they are only added
+    // to new calls.
+    let new_call = manager.get(guild_id).is_none();
+    let handler_lock = manager.join(guild_id, channel_id).await?;
+    let mut handler = handler_lock.lock().await;
+    let current_channel = match handler.current_channel() {
+        Some(channel) => channel.to_string(),
+        None => return Err(NooqieError::NotConnected),
+    };
 
-        debug!("{}: joined channel", current_channel);
+    debug!("{}: joined channel", current_channel);
+    if new_call {
         handler.add_global_event(TrackEvent::Error.into(), TrackErrorNotifier);
     }
+    Ok(())
+}
 
-    let http_client = {
-        let data = ctx.serenity_context().data.read().await;
-        data.get::<HttpKey>()
-            .cloned()
-            .expect("Guaranteed to exist in the typemap.")
+/// Adds `tracks` to the queue of `guild_id` with their info in the typemap,
+/// until the queue is full, right after the current track when `next` is set.
+/// Returns how many were queued.
+async fn enqueue(
+    ctx: Context<'_>,
+    manager: &Songbird,
+    guild_id: GuildId,
+    tracks: Vec<(Input, TrackInfo)>,
+    next: bool,
+) -> Result<usize, Error> {
+    let handler_lock = manager.get(guild_id).ok_or(NooqieError::NotConnected)?;
+    let mut handler = handler_lock.lock().await;
+    let current_channel = match handler.current_channel() {
+        Some(channel) => channel.to_string(),
+        None => return Err(NooqieError::NotConnected),
     };
 
-    if let Some(handler_lock) = manager.get(guild_id) {
-        let mut handler = handler_lock.lock().await;
-        let current_channel = match handler.current_channel() {
-            Some(channel) => channel.to_string(),
-            None => {
-                warn!("user not in voice channel, aborting");
-                return Ok(());
+    let max_queue = ctx.data().config.get().voice.max_queue;
+    let free = max_queue.saturating_sub(handler.queue().len());
+    if free == 0 {
+        return Err(NooqieError::QueueFull(max_queue));
+    }
+
+    let volume = track_volume(ctx.data(), guild_id)?;
+    let mut queued = 0;
+    for (src, info) in tracks.into_iter().take(free) {
+        let info = Arc::new(info);
+        // The preload time comes from the known duration, so enqueueing doesn't
+        // make songbird run yt-dlp for every track while the call is locked.
+        let preload = info
+            .duration
+            .map(|duration| duration.saturating_sub(Duration::from_secs(5)));
+        let track = Track::new(src).volume(volume);
+        let song: TrackHandle = handler.enqueue_with_preload(track, preload);
+        if next {
+            // The call stays locked, so nobody else queued anything since.
+            handler.queue().modify_queue(|queue| {
+                let last = queue.len() - 1;
+                move_entry(queue, last, (queued + 1).min(last));
+            });
+        }
+        song.typemap()
+            .write()
+            .await
+            .insert::<TrackInfoKey>(info.clone());
+
+        let announced = Arc::new(AtomicBool::new(false));
+        for event in [TrackEvent::Playable, TrackEvent::Play, TrackEvent::End] {
+            let notifier = NowPlayingNotifier {
+                ctx: ctx.serenity_context().clone(),
+                guild_id,
+                queue: handler.queue().clone(),
+                info: info.clone(),
+                announced: announced.clone(),
+                idle: ctx.data().idle.clone(),
+                listening: ctx.data().listening.clone(),
+                presence: ctx.data().presence.clone(),
+                config: ctx.data().config.clone(),
+            };
+            if let Err(error) = song.add_event(event.into(), notifier) {
+                error!("failed to add {:?} handler to track: {}", event, error);
             }
-        };
+        }
+        queued += 1;
+    }
+    drop(handler);
+
+    info!("playing in {}", current_channel);
+    let timeout = ctx.data().config.get().voice.idle_timeout();
+    check_idle(
+        ctx.serenity_context(),
+        &ctx.data().idle,
+        &ctx.data().listening,
+        timeout,
+        guild_id,
+        None,
+    )
+    .await;
+    Ok(queued)
+}
 
-        let src = YoutubeDl::new(http_client, url);
-        let _song: TrackHandle = handler.enqueue_input(src.into()).await;
+/// Number of users other than bots in `channel_id`.
+fn listeners(ctx: &serenity::Context, guild_id: GuildId, channel_id: ChannelId) -> usize {
+    let Some(guild) = ctx.cache.guild(guild_id) else {
+        return 0;
+    };
+    guild
+        .voice_states
+        .values()
+        .filter(|state| state.channel_id == Some(channel_id))
+        .filter(|state| {
+            let bot = match &state.member {
+                Some(member) => member.user.bot,
+                None => ctx.cache.user(state.user_id).is_some_and(|user| user.bot),
+            };
+            !bot
+        })
+        .count()
+}
 
-        ctx.serenity_context().set_presence(
-            Some(ActivityData::custom("Darude -Sandstorm")),
-            OnlineStatus::DoNotDisturb,
-        );
+/// Schedules leaving the voice channel of `guild_id` after `timeout` while
+/// the queue is empty or nobody is listening, pausing playback in the latter
+/// case, and cancels it once that is no longer true. An empty queue doesn't
+/// count while `listen` is on. `finished` is a track that just ended and may
+/// still be in the queue.
+pub async fn check_idle(
+    ctx: &serenity::Context,
+    idle: &IdleTimers,
+    listening: &Sessions,
+    timeout: Option<Duration>,
+    guild_id: GuildId,
+    finished: Option<&TrackHandle>,
+) {
+    let Some(manager) = songbird::get(ctx).await else {
+        return;
+    };
+    let Some(handler_lock) = manager.get(guild_id) else {
+        idle.forget(guild_id);
+        return;
+    };
+    let (channel, queue) = {
+        let handler = handler_lock.lock().await;
+        (handler.current_channel(), handler.queue().clone())
+    };
+    let Some(channel) = channel else {
+        return;
+    };
 
-        info!("playing in {}", current_channel);
-    } else {
-        warn!("no search available, aborting");
+    let alone = listeners(ctx, guild_id, ChannelId::new(channel.0.get())) == 0;
+    if alone {
+        if let Some(current) = queue.current() {
+            let playing = current
+                .get_info()
+                .await
+                .is_ok_and(|state| state.playing == PlayMode::Play);
+            if playing && queue.pause().is_ok() {
+                debug!("{}: nobody is listening, pausing", guild_id);
+                idle.mark_paused(guild_id);
+            }
+        }
+    } else if idle.take_paused(guild_id) {
+        debug!("{}: listeners are back, resuming", guild_id);
+        let _ = queue.resume();
     }
 
+    let empty = queue
+        .current_queue()
+        .iter()
+        .all(|track| finished.is_some_and(|finished| finished.uuid() == track.uuid()));
+    if !alone && (!empty || listening.is_active(guild_id)) {
+        if idle.cancel(guild_id) {
+            debug!("{}: no longer idle", guild_id);
+        }
+        return;
+    }
+    let Some(timeout) = timeout else {
+        return;
+    };
+    if !idle.is_scheduled(guild_id) {
+        debug!("{}: idle, leaving in {:?}", guild_id, timeout);
+    }
+    idle.schedule(guild_id, timeout, async move {
+        match manager.remove(guild_id).await {
+            Ok(()) => info!("{}: left idle voice channel", guild_id),
+            Err(error) => error!(
+                "{}: failed to leave idle voice channel: {}",
+                guild_id, error
+            ),
+        }
+    });
+}
+
+/// Volume tracks of `guild_id` play at, turned down to `tts.duck_volume`
+/// while answers are being spoken.
+fn track_volume(data: &Data, guild_id: GuildId) -> rusqlite::Result<f32> {
+    let mut volume = data.volumes.get(guild_id)? as f32 / 100.0;
+    if data.ducking.is_ducked(guild_id) {
+        volume *= data.config.get().tts.duck_volume as f32 / 100.0;
+    }
+    Ok(volume)
+}
+
+/// Restores the volume of the music once the last speech ducking it has
+/// finished.
+struct Unduck {
+    data: Data,
+    guild_id: GuildId,
+    queue: TrackQueue,
+    /// Shared by the end and error handlers of the same speech.
+    done: Arc<AtomicBool>,
+}
+
+#[async_trait]
+impl VoiceEventHandler for Unduck {
+    async fn act(&self, _ctx: &EventContext<'_>) -> Option<Event> {
+        if self.done.swap(true, Ordering::SeqCst) || !self.data.ducking.end(self.guild_id) {
+            return None;
+        }
+        match track_volume(&self.data, self.guild_id) {
+            Ok(volume) => {
+                for track in self.queue.current_queue() {
+                    let _ = track.set_volume(volume);
+                }
+            }
+            Err(error) => error!("{}: failed to restore volume: {}", self.guild_id, error),
+        }
+        None
+    }
+}
+
+/// Plays the `audio` of a spoken answer in `channel_id` on top of the queue,
+/// turning the music down to `tts.duck_volume` until it has been said.
+pub async fn speak(
+    ctx: Context<'_>,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    audio: Vec<u8>,
+) -> Result<(), Error> {
+    let manager = get_manager(ctx).await?;
+    join_voice(&manager, guild_id, channel_id).await?;
+    let handler_lock = manager.get(guild_id).ok_or(NooqieError::NotConnected)?;
+    let mut handler = handler_lock.lock().await;
+
+    ctx.data().ducking.start(guild_id);
+    let queue = handler.queue().clone();
+    let volume = match track_volume(ctx.data(), guild_id) {
+        Ok(volume) => volume,
+        Err(error) => {
+            ctx.data().ducking.end(guild_id);
+            return Err(error.into());
+        }
+    };
+    for track in queue.current_queue() {
+        let _ = track.set_volume(volume);
+    }
+
+    let speech = handler.play_input(audio.into());
+    let done = Arc::new(AtomicBool::new(false));
+    for event in [TrackEvent::End, TrackEvent::Error] {
+        let unduck = Unduck {
+            data: ctx.data().clone(),
+            guild_id,
+            queue: queue.clone(),
+            done: done.clone(),
+        };
+        if let Err(error) = speech.add_event(event.into(), unduck) {
+            error!("failed to add {:?} handler to speech: {}", event, error);
+        }
+    }
+    debug!("{}: speaking answer", guild_id);
+    drop(handler);
+
+    let timeout = ctx.data().config.get().voice.idle_timeout();
+    check_idle(
+        ctx.serenity_context(),
+        &ctx.data().idle,
+        &ctx.data().listening,
+        timeout,
+        guild_id,
+        None,
+    )
+    .await;
     Ok(())
 }
 
+/// Lists the first `limit` entries of the playlist at `url` without
+/// resolving them, they are only fetched once it is their turn to play.
+async fn flat_playlist(url: &str, limit: usize) -> Result<FlatPlaylist, Error> {
+    let output = Command::new("yt-dlp")
+        .args(["--flat-playlist", "-J", "--playlist-end"])
+        .arg(limit.to_string())
+        .arg(url)
+        .output()
+        .await
+        .map_err(|error| NooqieError::YtDlp(error.to_string()))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let reason = stderr.lines().last().unwrap_or("unknown error");
+        return Err(NooqieError::YtDlp(reason.to_string()));
+    }
+    let mut playlist: FlatPlaylist = serde_json::from_slice(&output.stdout)?;
+    playlist.entries.truncate(limit);
+    Ok(playlist)
+}
+
+/// URL of a search result or track, `fallback` when yt-dlp didn't report one.
+fn metadata_url(metadata: &AuxMetadata, fallback: &str) -> String {
+    metadata
+        .source_url
+        .clone()
+        .unwrap_or_else(|| fallback.to_string())
+}
+
+/// Searches YouTube for `query` and lets the author pick one of the results
+/// from a select menu. Returns `None` when nothing was picked in time.
+async fn pick_search_result(
+    ctx: Context<'_>,
+    http_client: HttpClient,
+    query: &str,
+) -> Result<Option<(YoutubeDl, AuxMetadata)>, Error> {
+    ctx.defer().await?;
+    let mut search = YoutubeDl::new_search(http_client.clone(), query.to_string());
+    let mut results: Vec<AuxMetadata> = search
+        .search(Some(SEARCH_RESULTS))
+        .await
+        .map_err(|error| NooqieError::YtDlp(error.to_string()))?
+        .into_iter()
+        .filter(|result| result.source_url.is_some())
+        .collect();
+    if results.is_empty() {
+        return Err(NooqieError::InvalidArgument(format!(
+            "no results for `{query}`"
+        )));
+    }
+
+    let custom_id = format!("{}search", ctx.id());
+    let options = results
+        .iter()
+        .enumerate()
+        .map(|(index, result)| {
+            let (label, description) = search_option(result);
+            CreateSelectMenuOption::new(label, index.to_string()).description(description)
+        })
+        .collect();
+    let menu = CreateSelectMenu::new(&custom_id, CreateSelectMenuKind::String { options })
+        .placeholder("pick a track");
+    let handle = ctx
+        .send(
+            CreateReply::default()
+                .content(format!("results for `{query}`:"))
+                .components(vec![CreateActionRow::SelectMenu(menu)]),
+        )
+        .await?;
+    let message = handle.message().await?;
+
+    let press = ComponentInteractionCollector::new(ctx)
+        .message_id(message.id)
+        .author_id(ctx.author().id)
+        .custom_ids(vec![custom_id])
+        .timeout(SEARCH_TIMEOUT)
+        .await;
+    let Some(press) = press else {
+        handle
+            .edit(
+                ctx,
+                CreateReply::default()
+                    .content("search timed out")
+                    .components(Vec::new()),
+            )
+            .await?;
+        return Ok(None);
+    };
+
+    let index = match &press.data.kind {
+        ComponentInteractionDataKind::StringSelect { values } => {
+            values.first().and_then(|value| value.parse::<usize>().ok())
+        }
+        _ => None,
+    };
+    let Some(index) = index.filter(|index| *index < results.len()) else {
+        return Ok(None);
+    };
+    let result = results.swap_remove(index);
+    press
+        .create_response(
+            ctx.serenity_context(),
+            CreateInteractionResponse::UpdateMessage(
+                CreateInteractionResponseMessage::new()
+                    .content(format!("queued `{}`", search_option(&result).0))
+                    .components(Vec::new()),
+            ),
+        )
+        .await?;
+
+    let url = metadata_url(&result, query);
+    Ok(Some((YoutubeDl::new(http_client, url), result)))
+}
+
+/// Label and description of a search result in the select menu.
+fn search_option(result: &AuxMetadata) -> (String, String) {
+    let title = result
+        .title
+        .clone()
+        .unwrap_or_else(|| String::from("untitled"));
+    let duration = match result.duration {
+        Some(duration) => format_duration(duration),
+        None => String::from("live"),
+    };
+    let description = match &result.channel {
+        Some(channel) => format!("{duration} · {channel}"),
+        None => duration,
+    };
+    (
+        truncate(&title, MAX_OPTION_LENGTH),
+        truncate(&description, MAX_OPTION_LENGTH),
+    )
+}
+
 #[poise::command(
     prefix_command,
     track_edits,
@@ -264,37 +875,22 @@ pub async fn play(
     help_text_fn = skip_help
 )]
 pub async fn skip(ctx: Context<'_>) -> Result<(), Error> {
-    let guild_id = match get_voice_info(ctx).await {
-        Ok(info) => info.0,
-        Err(err) => {
-            error!("{err}");
-            return Ok(());
-        }
-    };
+    let (guild_id, _) = get_voice_info(ctx).await?;
 
-    let manager = match get_manager(ctx).await {
-        Ok(manager) => manager,
-        Err(err) => {
-            error!("{err}");
-            return Ok(());
-        }
-    };
+    let manager = get_manager(ctx).await?;
 
     if let Some(handler_lock) = manager.get(guild_id) {
         let handler = handler_lock.lock().await;
         let current_channel = match handler.current_channel() {
             Some(channel) => channel.to_string(),
-            None => {
-                warn!("user not in voice channel, aborting");
-                return Ok(());
-            }
+            None => return Err(NooqieError::NotConnected),
         };
 
         let queue = handler.queue();
         debug!("{}: skipping audio track", current_channel);
         let _ = queue.skip();
     } else {
-        warn!("failed to skip audio track, aborting");
+        return Err(NooqieError::NotConnected);
     }
 
     Ok(())
@@ -310,37 +906,22 @@ pub async fn skip(ctx: Context<'_>) -> Result<(), Error> {
     help_text_fn = clear_help
 )]
 pub async fn clear(ctx: Context<'_>) -> Result<(), Error> {
-    let guild_id = match get_voice_info(ctx).await {
-        Ok(info) => info.0,
-        Err(err) => {
-            error!("{err}");
-            return Ok(());
-        }
-    };
+    let (guild_id, _) = get_voice_info(ctx).await?;
 
-    let manager = match get_manager(ctx).await {
-        Ok(manager) => manager,
-        Err(err) => {
-            error!("{err}");
-            return Ok(());
-        }
-    };
+    let manager = get_manager(ctx).await?;
 
     if let Some(handler_lock) = manager.get(guild_id) {
         let handler = handler_lock.lock().await;
         let current_channel = match handler.current_channel() {
             Some(channel) => channel.to_string(),
-            None => {
-                warn!("user not in voice channel, aborting");
-                return Ok(());
-            }
+            None => return Err(NooqieError::NotConnected),
         };
 
         let queue = handler.queue();
         queue.stop();
         debug!("{}: queue cleared", current_channel);
     } else {
-        warn!("failed to clear queue");
+        return Err(NooqieError::NotConnected);
     }
 
     Ok(())
@@ -356,37 +937,22 @@ pub async fn clear(ctx: Context<'_>) -> Result<(), Error> {
     help_text_fn = pause_help
 )]
 pub async fn pause(ctx: Context<'_>) -> Result<(), Error> {
-    let guild_id = match get_voice_info(ctx).await {
-        Ok(info) => info.0,
-        Err(err) => {
-            error!("{err}");
-            return Ok(());
-        }
-    };
+    let (guild_id, _) = get_voice_info(ctx).await?;
 
-    let manager = match get_manager(ctx).await {
-        Ok(manager) => manager,
-        Err(err) => {
-            error!("{err}");
-            return Ok(());
-        }
-    };
+    let manager = get_manager(ctx).await?;
 
     if let Some(handler_lock) = manager.get(guild_id) {
         let handler = handler_lock.lock().await;
         let current_channel = match handler.current_channel() {
             Some(channel) => channel.to_string(),
-            None => {
-                warn!("user not in voice channel, aborting");
-                return Ok(());
-            }
+            None => return Err(NooqieError::NotConnected),
         };
 
         let queue = handler.queue();
         debug!("{}: pausing audio track", current_channel);
         let _ = queue.pause();
     } else {
-        warn!("failed to pause audio track");
+        return Err(NooqieError::NotConnected);
     }
 
     Ok(())
@@ -402,37 +968,22 @@ pub async fn pause(ctx: Context<'_>) -> Result<(), Error> {
     help_text_fn = resume_help
 )]
 pub async fn resume(ctx: Context<'_>) -> Result<(), Error> {
-    let guild_id = match get_voice_info(ctx).await {
-        Ok(info) => info.0,
-        Err(err) => {
-            error!("{err}");
-            return Ok(());
-        }
-    };
+    let (guild_id, _) = get_voice_info(ctx).await?;
 
-    let manager = match get_manager(ctx).await {
-        Ok(manager) => manager,
-        Err(err) => {
-            error!("{err}");
-            return Ok(());
-        }
-    };
+    let manager = get_manager(ctx).await?;
 
     if let Some(handler_lock) = manager.get(guild_id) {
         let handler = handler_lock.lock().await;
         let current_channel = match handler.current_channel() {
             Some(channel) => channel.to_string(),
-            None => {
-                warn!("user not in voice channel, aborting");
-                return Ok(());
-            }
+            None => return Err(NooqieError::NotConnected),
         };
 
         let queue = handler.queue();
         debug!("{}: resuming audio track", current_channel);
         let _ = queue.resume();
     } else {
-        warn!("failed to resume audio track, aborting");
+        return Err(NooqieError::NotConnected);
     }
 
     Ok(())
@@ -451,21 +1002,9 @@ pub async fn loop_track(
     ctx: Context<'_>,
     #[description = "Amount"] msg: Option<String>,
 ) -> Result<(), Error> {
-    let guild_id = match get_voice_info(ctx).await {
-        Ok(info) => info.0,
-        Err(err) => {
-            error!("{err}");
-            return Ok(());
-        }
-    };
+    let (guild_id, _) = get_voice_info(ctx).await?;
 
-    let manager = match get_manager(ctx).await {
-        Ok(manager) => manager,
-        Err(err) => {
-            error!("{err}");
-            return Ok(());
-        }
-    };
+    let manager = get_manager(ctx).await?;
 
     let amount = match msg {
         Some(msg) => msg,
@@ -476,7 +1015,9 @@ pub async fn loop_track(
         Ok(loops) => loops,
         Err(error) => {
             warn!("unable to parse loop amount: {}", error);
-            return Ok(());
+            return Err(NooqieError::InvalidArgument(format!(
+                "`{amount}` is not a number of loops"
+            )));
         }
     };
 
@@ -484,20 +1025,11 @@ pub async fn loop_track(
         let handler = handler_lock.lock().await;
         let current_channel = match handler.current_channel() {
             Some(channel) => channel.to_string(),
-            None => {
-                warn!("user not in voice channel, aborting");
-                return Ok(());
-            }
+            None => return Err(NooqieError::NotConnected),
         };
 
         let queue = handler.queue();
-        let current = match queue.current() {
-            Some(current) => current,
-            None => {
-                warn!("no track to loop");
-                return Ok(());
-            }
-        };
+        let current = queue.current().ok_or(NooqieError::NothingPlaying)?;
         if loops == 0 {
             debug!("{}: looping audio track", current_channel);
             let _ = current.enable_loop();
@@ -506,12 +1038,454 @@ pub async fn loop_track(
             let _ = current.loop_for(loops);
         }
     } else {
-        warn!("failed to loop audio track, aborting");
+        return Err(NooqieError::NotConnected);
+    }
+
+    Ok(())
+}
+
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only = true,
+    aliases("q"),
+    category = "Voice",
+    help_text_fn = queue_help
+)]
+pub async fn queue(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(NooqieError::NotInGuild)?;
+
+    let manager = get_manager(ctx).await?;
+
+    let handler_lock = manager.get(guild_id).ok_or(NooqieError::NotConnected)?;
+    let tracks = handler_lock.lock().await.queue().current_queue();
+    if tracks.is_empty() {
+        return Err(NooqieError::NothingPlaying);
+    }
+
+    let mut infos = Vec::with_capacity(tracks.len());
+    for track in &tracks {
+        infos.push(track_info(track).await);
+    }
+    let pages = queue_pages(&infos, QUEUE_PAGE_SIZE);
+    paginate(ctx, "Queue", &pages).await
+}
+
+async fn get_queue(ctx: Context<'_>) -> Result<TrackQueue, Error> {
+    let (guild_id, _) = get_voice_info(ctx).await?;
+
+    let manager = get_manager(ctx).await?;
+
+    let handler_lock = manager.get(guild_id).ok_or(NooqieError::NotConnected)?;
+    let handler = handler_lock.lock().await;
+    Ok(handler.queue().clone())
+}
+
+/// Whether the author may change tracks requested by other people, which
+/// takes the `voice.dj_role` role or the Manage Server permission.
+async fn is_dj(ctx: Context<'_>) -> bool {
+    let dj_role = ctx.data().config.get().voice.dj_role.clone();
+    let Some(member) = ctx.author_member().await else {
+        return false;
+    };
+    let Some(guild) = ctx.guild() else {
+        return false;
+    };
+    guild.member_permissions(&member).manage_guild()
+        || member.roles.iter().any(|role_id| {
+            guild
+                .roles
+                .get(role_id)
+                .is_some_and(|role| role.name.eq_ignore_ascii_case(&dj_role))
+        })
+}
+
+/// Fails unless every one of `tracks` was requested by the author, or the
+/// author is a DJ.
+async fn check_requester(ctx: Context<'_>, tracks: &[TrackHandle]) -> Result<(), Error> {
+    for track in tracks {
+        let requester = track_info(track).await.map(|info| info.requester);
+        if requester != Some(ctx.author().id) && !is_dj(ctx).await {
+            return Err(NooqieError::NotRequester);
+        }
+    }
+    Ok(())
+}
+
+/// Title of `track` to show in replies.
+async fn track_title(track: &TrackHandle) -> String {
+    match track_info(track).await {
+        Some(info) => info.title.clone(),
+        None => String::from("unknown track"),
+    }
+}
+
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only = true,
+    aliases("rm"),
+    category = "Voice",
+    help_text_fn = remove_help
+)]
+pub async fn remove(
+    ctx: Context<'_>,
+    #[description = "Position in the queue"] index: usize,
+) -> Result<(), Error> {
+    let queue = get_queue(ctx).await?;
+    if index == 0 {
+        return Err(NooqieError::InvalidArgument(String::from(
+            "use `skip` to remove the current track",
+        )));
+    }
+    let track = queue
+        .current_queue()
+        .get(index)
+        .cloned()
+        .ok_or(NooqieError::InvalidPosition(index))?;
+    check_requester(ctx, std::slice::from_ref(&track)).await?;
+
+    let removed = queue.modify_queue(|queue| {
+        let position = queue
+            .iter()
+            .position(|queued| queued.uuid() == track.uuid())?;
+        queue.remove(position)
+    });
+    let removed = removed.ok_or(NooqieError::InvalidPosition(index))?;
+    let _ = removed.stop();
+
+    debug!("{}: removed track {}", ctx.channel_id(), index);
+    ctx.say(format!("removed `{}`", track_title(&track).await))
+        .await?;
+    Ok(())
+}
+
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only = true,
+    rename = "move",
+    aliases("mv"),
+    category = "Voice",
+    help_text_fn = move_help
+)]
+pub async fn move_track(
+    ctx: Context<'_>,
+    #[description = "Position of the track"] from: usize,
+    #[description = "New position"] to: usize,
+) -> Result<(), Error> {
+    let queue = get_queue(ctx).await?;
+    if from == 0 || to == 0 {
+        return Err(NooqieError::InvalidArgument(String::from(
+            "the current track can't be moved",
+        )));
     }
 
+    let moved = queue.modify_queue(|queue| {
+        let track = queue.get(from)?.handle();
+        move_entry(queue, from, to).then_some(track)
+    });
+    let track = moved.ok_or(NooqieError::InvalidPosition(from.max(to)))?;
+
+    debug!("{}: moved track {} to {}", ctx.channel_id(), from, to);
+    ctx.say(format!("moved `{}` to {to}", track_title(&track).await))
+        .await?;
+    Ok(())
+}
+
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only = true,
+    aliases("mix"),
+    category = "Voice",
+    help_text_fn = shuffle_help
+)]
+pub async fn shuffle(ctx: Context<'_>) -> Result<(), Error> {
+    let queue = get_queue(ctx).await?;
+    let shuffled = queue.modify_queue(|queue| {
+        shuffle_upcoming(queue, &mut rand::thread_rng());
+        queue.len().saturating_sub(1)
+    });
+
+    debug!("{}: shuffled {} tracks", ctx.channel_id(), shuffled);
+    ctx.say(format!("shuffled {shuffled} tracks")).await?;
     Ok(())
 }
 
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only = true,
+    aliases("jump"),
+    category = "Voice",
+    help_text_fn = skipto_help
+)]
+pub async fn skipto(
+    ctx: Context<'_>,
+    #[description = "Position in the queue"] index: usize,
+) -> Result<(), Error> {
+    let queue = get_queue(ctx).await?;
+    let tracks = queue.current_queue();
+    if index == 0 || index >= tracks.len() {
+        return Err(NooqieError::InvalidPosition(index));
+    }
+    check_requester(ctx, &tracks[..index]).await?;
+
+    let target = tracks[index].uuid();
+    let skipped: Vec<Queued> =
+        queue.modify_queue(
+            |queue| match queue.iter().position(|queued| queued.uuid() == target) {
+                Some(position) if position > 1 => queue.drain(1..position).collect(),
+                _ => Vec::new(),
+            },
+        );
+    for track in &skipped {
+        let _ = track.stop();
+    }
+    let _ = queue.skip();
+
+    debug!("{}: skipped to track {}", ctx.channel_id(), index);
+    ctx.say(format!(
+        "skipping to `{}`",
+        track_title(&tracks[index]).await
+    ))
+    .await?;
+    Ok(())
+}
+
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only = true,
+    aliases("playtop"),
+    category = "Voice",
+    help_text_fn = playnext_help
+)]
+pub async fn playnext(
+    ctx: Context<'_>,
+    #[description = "YouTube URL, audio file URL, sound name or search terms"]
+    #[rest]
+    msg: Option<String>,
+) -> Result<(), Error> {
+    let (guild_id, connect_to) = get_voice_info(ctx).await?;
+
+    let manager = get_manager(ctx).await?;
+
+    let query = msg.ok_or(NooqieError::MissingArgument(
+        "YouTube URL, audio file URL, sound name or search terms",
+    ))?;
+    let soundboard = ctx.data().config.get().voice.soundboard.clone();
+    let source = Source::of(&query, soundboard.as_deref());
+
+    let http_client = http_client(ctx).await;
+    let Some(track) = resolve_track(ctx, http_client, source).await? else {
+        return Ok(());
+    };
+    let title = track.1.title.clone();
+
+    join_voice(&manager, guild_id, connect_to).await?;
+    enqueue(ctx, &manager, guild_id, vec![track], true).await?;
+
+    debug!("{}: playing '{}' next", ctx.channel_id(), title);
+    ctx.say(format!("playing `{title}` next")).await?;
+    Ok(())
+}
+
+/// Moves the current track to `position`, clamped to its duration.
+async fn seek_current(ctx: Context<'_>, position: Duration) -> Result<Duration, Error> {
+    let queue = get_queue(ctx).await?;
+    let current = queue.current().ok_or(NooqieError::NothingPlaying)?;
+    let position = match track_info(&current).await.and_then(|info| info.duration) {
+        Some(duration) => position.min(duration),
+        None => position,
+    };
+    current.seek_async(position).await.map_err(|error| {
+        warn!("failed to seek track {:?}: {}", current.uuid(), error);
+        NooqieError::InvalidArgument(String::from("I can't seek in this track"))
+    })
+}
+
+/// Current playback position of the playing track.
+async fn current_position(ctx: Context<'_>) -> Result<Duration, Error> {
+    let queue = get_queue(ctx).await?;
+    let current = queue.current().ok_or(NooqieError::NothingPlaying)?;
+    let state = current
+        .get_info()
+        .await
+        .map_err(|_| NooqieError::NothingPlaying)?;
+    Ok(state.position)
+}
+
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only = true,
+    category = "Voice",
+    help_text_fn = seek_help
+)]
+pub async fn seek(
+    ctx: Context<'_>,
+    #[description = "Position, e.g. 1:30"] position: String,
+) -> Result<(), Error> {
+    let position = parse_timestamp(&position).ok_or_else(|| {
+        NooqieError::InvalidArgument(format!("`{position}` is not a position like `1:30`"))
+    })?;
+    let position = seek_current(ctx, position).await?;
+    debug!("{}: seeked to {:?}", ctx.channel_id(), position);
+    ctx.say(format!("seeked to {}", format_duration(position)))
+        .await?;
+    Ok(())
+}
+
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only = true,
+    aliases("ff"),
+    category = "Voice",
+    help_text_fn = forward_help
+)]
+pub async fn forward(
+    ctx: Context<'_>,
+    #[description = "Seconds to skip"] seconds: Option<u64>,
+) -> Result<(), Error> {
+    let seconds = Duration::from_secs(seconds.unwrap_or(DEFAULT_SKIP_SECONDS));
+    let position = current_position(ctx).await?.saturating_add(seconds);
+    let position = seek_current(ctx, position).await?;
+    ctx.say(format!("seeked to {}", format_duration(position)))
+        .await?;
+    Ok(())
+}
+
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only = true,
+    aliases("rw"),
+    category = "Voice",
+    help_text_fn = rewind_help
+)]
+pub async fn rewind(
+    ctx: Context<'_>,
+    #[description = "Seconds to go back"] seconds: Option<u64>,
+) -> Result<(), Error> {
+    let seconds = Duration::from_secs(seconds.unwrap_or(DEFAULT_SKIP_SECONDS));
+    let position = current_position(ctx).await?.saturating_sub(seconds);
+    let position = seek_current(ctx, position).await?;
+    ctx.say(format!("seeked to {}", format_duration(position)))
+        .await?;
+    Ok(())
+}
+
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only = true,
+    aliases("vol"),
+    category = "Voice",
+    help_text_fn = volume_help
+)]
+pub async fn volume(
+    ctx: Context<'_>,
+    #[description = "Volume in percent, 0 to 200"] percent: Option<u32>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(NooqieError::NotInGuild)?;
+    let Some(percent) = percent else {
+        let volume = ctx.data().volumes.get(guild_id)?;
+        ctx.say(format!("volume is {volume}%")).await?;
+        return Ok(());
+    };
+    if percent > MAX_VOLUME {
+        return Err(NooqieError::InvalidArgument(format!(
+            "the volume goes from 0 to {MAX_VOLUME}%"
+        )));
+    }
+
+    ctx.data().volumes.set(guild_id, percent)?;
+    let volume = track_volume(ctx.data(), guild_id)?;
+    let manager = get_manager(ctx).await?;
+    if let Some(handler_lock) = manager.get(guild_id) {
+        let handler = handler_lock.lock().await;
+        for track in handler.queue().current_queue() {
+            let _ = track.set_volume(volume);
+        }
+    }
+
+    debug!("{}: volume set to {}%", guild_id, percent);
+    ctx.say(format!("volume set to {percent}%")).await?;
+    Ok(())
+}
+
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only = true,
+    aliases("np", "current"),
+    category = "Voice",
+    help_text_fn = nowplaying_help
+)]
+pub async fn nowplaying(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(NooqieError::NotInGuild)?;
+
+    let manager = get_manager(ctx).await?;
+
+    let handler_lock = manager.get(guild_id).ok_or(NooqieError::NotConnected)?;
+    let current = handler_lock
+        .lock()
+        .await
+        .queue()
+        .current()
+        .ok_or(NooqieError::NothingPlaying)?;
+    let info = track_info(&current)
+        .await
+        .ok_or(NooqieError::NothingPlaying)?;
+    let state = current
+        .get_info()
+        .await
+        .map_err(|_| NooqieError::NothingPlaying)?;
+
+    let progress = progress_bar(state.position, info.duration, PROGRESS_BAR_WIDTH);
+    let embed = now_playing_embed(&info)
+        .field("Progress", progress, false)
+        .field(
+            "Volume",
+            format!("{}%", (state.volume * 100.0).round()),
+            true,
+        );
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+#[poise::command(
+    prefix_command,
+    slash_command,
+    guild_only = true,
+    aliases("soundboard"),
+    category = "Voice",
+    help_text_fn = sounds_help
+)]
+pub async fn sounds(ctx: Context<'_>) -> Result<(), Error> {
+    let Some(soundboard) = ctx.data().config.get().voice.soundboard.clone() else {
+        ctx.say("there's no soundboard").await?;
+        return Ok(());
+    };
+    let names: Vec<String> = list_sounds(&soundboard)
+        .iter()
+        .map(|path| format!("`{}`", sound_name(path)))
+        .collect();
+    if names.is_empty() {
+        ctx.say("the soundboard is empty").await?;
+        return Ok(());
+    }
+
+    let pages: Vec<String> = names
+        .chunks(SOUNDS_PAGE_SIZE)
+        .map(|chunk| chunk.join(" "))
+        .collect();
+    paginate(ctx, "Soundboard", &pages).await
+}
+
 pub fn join_help() -> String {
     String::from("joins current voice channel")
 }
@@ -521,7 +1495,10 @@ pub fn leave_help() -> String {
 }
 
 pub fn play_help() -> String {
-    String::from("plays audio track from YouTube link")
+    String::from(
+        "plays audio track or playlist from a YouTube link, an audio file link or upload, or a \
+         sound from the soundboard, or searches YouTube for it",
+    )
 }
 
 pub fn skip_help() -> String {
@@ -540,6 +1517,54 @@ pub fn resume_help() -> String {
     String::from("resumes current audio track")
 }
 
+pub fn remove_help() -> String {
+    String::from("removes a track from the queue")
+}
+
+pub fn move_help() -> String {
+    String::from("moves a track to another position in the queue")
+}
+
+pub fn shuffle_help() -> String {
+    String::from("shuffles the upcoming audio tracks")
+}
+
+pub fn skipto_help() -> String {
+    String::from("skips ahead to a track in the queue")
+}
+
+pub fn playnext_help() -> String {
+    String::from("queues an audio track to play after the current one")
+}
+
+pub fn seek_help() -> String {
+    String::from("jumps to a position in the current audio track")
+}
+
+pub fn forward_help() -> String {
+    String::from("skips ahead in the current audio track")
+}
+
+pub fn rewind_help() -> String {
+    String::from("goes back in the current audio track")
+}
+
+pub fn volume_help() -> String {
+    String::from("shows or sets the playback volume of this server")
+}
+
+pub fn nowplaying_help() -> String {
+    String::from("shows the current audio track and its progress")
+}
+
+pub fn sounds_help() -> String {
+    String::from("lists the sounds play can play by name")
+}
+
+pub fn queue_help() -> String {
+    String::from("lists the current and upcoming audio tracks")
+}
+
 pub fn loop_help() -> String {
     String::from("loops current audio track")
 }
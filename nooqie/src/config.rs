@@ -0,0 +1,535 @@
+use log::LevelFilter;
+
+use poise::serenity_prelude::GuildId;
+
+use serde::Deserialize;
+
+use std::{
+    collections::HashMap,
+    env, fmt, fs, io,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub bot: BotConfig,
+    pub ollama: OllamaConfig,
+    pub voice: VoiceConfig,
+    pub tts: TtsConfig,
+    pub listen: ListenConfig,
+    pub limits: LimitsConfig,
+    pub storage: StorageConfig,
+    pub logging: LoggingConfig,
+    /// Inference servers guilds can use instead of the `ollama` one.
+    pub backends: HashMap<String, BackendConfig>,
+    /// Settings of single guilds, by guild id.
+    pub guilds: HashMap<String, GuildConfig>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct BotConfig {
+    pub token: String,
+    pub prefix: String,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct OllamaConfig {
+    /// Base URL of the Ollama server, without the `/api/...` path.
+    pub url: String,
+    /// Model used in guilds that did not select one.
+    pub model: String,
+    /// Seconds of inactivity before a channel's conversation is forgotten.
+    pub context_timeout: u64,
+    /// Prompts and answers a conversation remembers, older ones are
+    /// forgotten first. 0 remembers all of them.
+    pub max_turns: usize,
+    /// Wire format the server at `url` speaks.
+    pub api: Api,
+    /// Bearer token sent to OpenAI compatible servers.
+    pub api_key: Option<String>,
+    /// Whether the model of an OpenAI compatible server can see images,
+    /// which those servers don't tell.
+    pub vision: bool,
+}
+
+/// API of an inference server.
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Api {
+    /// Ollama's `/api/chat`.
+    #[default]
+    Ollama,
+    /// `/v1/chat/completions` as served by vLLM, llama.cpp and others.
+    OpenAi,
+}
+
+#[derive(Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct BackendConfig {
+    pub api: Api,
+    /// Base URL, without `/api/...` for Ollama and including `/v1` otherwise.
+    pub url: String,
+    /// Model used in guilds that did not select one, `ollama.model` if unset.
+    pub model: Option<String>,
+    pub api_key: Option<String>,
+    pub vision: bool,
+}
+
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct GuildConfig {
+    /// Name of the entry of `backends` the guild uses.
+    pub backend: Option<String>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct VoiceConfig {
+    /// Maximum number of tracks in a guild's queue.
+    pub max_queue: usize,
+    /// Maximum number of tracks queued from a single playlist link.
+    pub max_playlist: usize,
+    /// Name of the role allowed to change tracks requested by other people.
+    pub dj_role: String,
+    /// Seconds before leaving a voice channel with an empty queue or no
+    /// listeners, 0 stays forever.
+    pub idle_timeout: u64,
+    /// Directory of audio files `play` can play by name.
+    pub soundboard: Option<PathBuf>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct TtsConfig {
+    /// Program and arguments reading text on stdin and writing WAV to stdout.
+    pub command: Vec<String>,
+    /// Music volume while speaking, in percent of the guild's volume.
+    pub duck_volume: u32,
+    /// Longest answer spoken, in characters, the rest is only written.
+    pub max_length: usize,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct ListenConfig {
+    /// Transcription endpoint taking the OpenAI audio transcription form.
+    pub url: String,
+    /// Model name sent along with the audio.
+    pub model: String,
+    /// Only utterances starting with this are answered.
+    pub wake_word: String,
+    /// Loudness above which audio counts as speech.
+    pub threshold: f64,
+    /// Milliseconds of silence that end an utterance.
+    pub silence: u64,
+    /// Milliseconds of speech an utterance needs to be transcribed.
+    pub min_speech: u64,
+    /// Seconds after which an utterance is cut off.
+    pub max_utterance: u64,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitsConfig {
+    /// Number of LLM requests running at once, the rest wait in line.
+    pub concurrency: usize,
+    /// Seconds a user has to wait between LLM requests.
+    pub cooldown: u64,
+    /// Tokens a guild can generate per `budget_window`, 0 is unlimited.
+    pub guild_budget: u64,
+    /// Seconds over which `guild_budget` is counted.
+    pub budget_window: u64,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageConfig {
+    /// SQLite database file, only read at startup.
+    pub path: PathBuf,
+}
+
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoggingConfig {
+    /// Log level of nooqie, `RUST_LOG` is used when unset.
+    pub level: Option<String>,
+}
+
+impl Default for BotConfig {
+    fn default() -> Self {
+        BotConfig {
+            token: String::new(),
+            prefix: String::from("!"),
+        }
+    }
+}
+
+impl Default for OllamaConfig {
+    fn default() -> Self {
+        OllamaConfig {
+            url: String::from("http://localhost:11434"),
+            model: String::from("llama2-uncensored"),
+            context_timeout: 600,
+            max_turns: 20,
+            api: Api::Ollama,
+            api_key: None,
+            vision: false,
+        }
+    }
+}
+
+impl Default for VoiceConfig {
+    fn default() -> Self {
+        VoiceConfig {
+            max_queue: 100,
+            max_playlist: 50,
+            dj_role: String::from("DJ"),
+            idle_timeout: 300,
+            soundboard: None,
+        }
+    }
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        StorageConfig {
+            path: PathBuf::from("nooqie.db"),
+        }
+    }
+}
+
+impl OllamaConfig {
+    pub fn context_timeout(&self) -> Duration {
+        Duration::from_secs(self.context_timeout)
+    }
+
+    /// Turns a conversation remembers, if limited.
+    pub fn max_turns(&self) -> Option<usize> {
+        match self.max_turns {
+            0 => None,
+            turns => Some(turns),
+        }
+    }
+}
+
+impl Default for TtsConfig {
+    fn default() -> Self {
+        TtsConfig {
+            command: vec![String::from("espeak-ng"), String::from("--stdout")],
+            duck_volume: 20,
+            max_length: 1000,
+        }
+    }
+}
+
+impl Default for ListenConfig {
+    fn default() -> Self {
+        ListenConfig {
+            url: String::from("http://localhost:8080/inference"),
+            model: String::from("whisper-1"),
+            wake_word: String::from("nooqie"),
+            threshold: 500.0,
+            silence: 800,
+            min_speech: 300,
+            max_utterance: 15,
+        }
+    }
+}
+
+impl ListenConfig {
+    pub fn silence(&self) -> Duration {
+        Duration::from_millis(self.silence)
+    }
+
+    pub fn min_speech(&self) -> Duration {
+        Duration::from_millis(self.min_speech)
+    }
+
+    pub fn max_utterance(&self) -> Duration {
+        Duration::from_secs(self.max_utterance)
+    }
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        LimitsConfig {
+            concurrency: 1,
+            cooldown: 5,
+            guild_budget: 0,
+            budget_window: 3600,
+        }
+    }
+}
+
+impl LimitsConfig {
+    pub fn cooldown(&self) -> Duration {
+        Duration::from_secs(self.cooldown)
+    }
+
+    /// Tokens a guild can generate per window, if limited.
+    pub fn guild_budget(&self) -> Option<u64> {
+        match self.guild_budget {
+            0 => None,
+            tokens => Some(tokens),
+        }
+    }
+
+    pub fn budget_window(&self) -> Duration {
+        Duration::from_secs(self.budget_window)
+    }
+}
+
+impl VoiceConfig {
+    /// How long to wait before leaving an idle voice channel, if at all.
+    pub fn idle_timeout(&self) -> Option<Duration> {
+        match self.idle_timeout {
+            0 => None,
+            seconds => Some(Duration::from_secs(seconds)),
+        }
+    }
+}
+
+impl LoggingConfig {
+    pub fn level_filter(&self) -> Option<LevelFilter> {
+        self.level
+            .as_deref()
+            .and_then(|level| LevelFilter::from_str(level).ok())
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(PathBuf, io::Error),
+    Parse(PathBuf, toml::de::Error),
+    Invalid(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(path, error) => write!(f, "{}: {error}", path.display()),
+            ConfigError::Parse(path, error) => write!(f, "{}: {error}", path.display()),
+            ConfigError::Invalid(reason) => write!(f, "invalid configuration: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Config {
+    /// Reads the configuration file at `path`, or the defaults when there is
+    /// none, then applies the environment variables and validates the result.
+    pub fn load(path: Option<&Path>) -> Result<Config, ConfigError> {
+        let mut config = match path {
+            Some(path) => {
+                let text = fs::read_to_string(path)
+                    .map_err(|error| ConfigError::Io(path.to_path_buf(), error))?;
+                Config::parse(&text)
+                    .map_err(|error| ConfigError::Parse(path.to_path_buf(), error))?
+            }
+            None => Config::default(),
+        };
+        config.apply_env();
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn parse(text: &str) -> Result<Config, toml::de::Error> {
+        toml::from_str(text)
+    }
+
+    /// Inference server used in `guild_id`, the `ollama` one unless the
+    /// guild picked another of `backends`.
+    pub fn backend(&self, guild_id: Option<GuildId>) -> BackendConfig {
+        let configured = guild_id
+            .and_then(|guild_id| self.guilds.get(&guild_id.to_string()))
+            .and_then(|guild| guild.backend.as_ref())
+            .and_then(|name| self.backends.get(name));
+        match configured {
+            Some(backend) => backend.clone(),
+            None => BackendConfig {
+                api: self.ollama.api,
+                url: self.ollama.url.clone(),
+                model: Some(self.ollama.model.clone()),
+                api_key: self.ollama.api_key.clone(),
+                vision: self.ollama.vision,
+            },
+        }
+    }
+
+    /// Overrides settings with the environment variables that are set.
+    pub fn apply_env(&mut self) {
+        if let Ok(token) = env::var("DISCORD_TOKEN") {
+            self.bot.token = token;
+        }
+        if let Ok(prefix) = env::var("NOOQIE_PREFIX") {
+            self.bot.prefix = prefix;
+        }
+        if let Ok(post_url) = env::var("OLLAMA_POST_URL") {
+            self.ollama.url = match post_url.find("/api/") {
+                Some(index) => post_url[..index].to_string(),
+                None => post_url,
+            };
+        }
+        if let Ok(model) = env::var("OLLAMA_MODEL") {
+            self.ollama.model = model;
+        }
+        if let Ok(path) = env::var("NOOQIE_DATABASE") {
+            self.storage.path = PathBuf::from(path);
+        }
+        if let Ok(timeout) = env::var("OLLAMA_CONTEXT_TIMEOUT") {
+            if let Ok(timeout) = timeout.parse() {
+                self.ollama.context_timeout = timeout;
+            }
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.bot.token.trim().is_empty() {
+            return Err(ConfigError::Invalid(String::from(
+                "bot.token or 'DISCORD_TOKEN' must be set",
+            )));
+        }
+        if self.bot.prefix.trim().is_empty() {
+            return Err(ConfigError::Invalid(String::from(
+                "bot.prefix must not be empty",
+            )));
+        }
+        match reqwest::Url::parse(&self.ollama.url) {
+            Ok(url) if url.scheme() == "http" || url.scheme() == "https" => {}
+            _ => {
+                return Err(ConfigError::Invalid(format!(
+                    "ollama.url '{}' is not a http(s) URL",
+                    self.ollama.url
+                )))
+            }
+        }
+        if self.ollama.model.trim().is_empty() {
+            return Err(ConfigError::Invalid(String::from(
+                "ollama.model must not be empty",
+            )));
+        }
+        for (name, backend) in &self.backends {
+            match reqwest::Url::parse(&backend.url) {
+                Ok(url) if url.scheme() == "http" || url.scheme() == "https" => {}
+                _ => {
+                    return Err(ConfigError::Invalid(format!(
+                        "backends.{name}.url '{}' is not a http(s) URL",
+                        backend.url
+                    )))
+                }
+            }
+        }
+        for (guild, settings) in &self.guilds {
+            if guild.parse::<u64>().is_err() {
+                return Err(ConfigError::Invalid(format!(
+                    "guilds.{guild} is not a guild id"
+                )));
+            }
+            if let Some(backend) = &settings.backend {
+                if !self.backends.contains_key(backend) {
+                    return Err(ConfigError::Invalid(format!(
+                        "guilds.{guild}.backend '{backend}' is not one of backends"
+                    )));
+                }
+            }
+        }
+        if self.voice.max_queue == 0 {
+            return Err(ConfigError::Invalid(String::from(
+                "voice.max_queue must be at least 1",
+            )));
+        }
+        if self.voice.max_playlist == 0 {
+            return Err(ConfigError::Invalid(String::from(
+                "voice.max_playlist must be at least 1",
+            )));
+        }
+        if self.tts.command.is_empty() || self.tts.command[0].trim().is_empty() {
+            return Err(ConfigError::Invalid(String::from(
+                "tts.command must name a program",
+            )));
+        }
+        if self.tts.duck_volume > 100 {
+            return Err(ConfigError::Invalid(String::from(
+                "tts.duck_volume must be between 0 and 100",
+            )));
+        }
+        match reqwest::Url::parse(&self.listen.url) {
+            Ok(url) if url.scheme() == "http" || url.scheme() == "https" => {}
+            _ => {
+                return Err(ConfigError::Invalid(format!(
+                    "listen.url '{}' is not a http(s) URL",
+                    self.listen.url
+                )))
+            }
+        }
+        if self.listen.wake_word.trim().is_empty() {
+            return Err(ConfigError::Invalid(String::from(
+                "listen.wake_word must not be empty",
+            )));
+        }
+        if self.listen.max_utterance == 0 {
+            return Err(ConfigError::Invalid(String::from(
+                "listen.max_utterance must be at least 1",
+            )));
+        }
+        if self.limits.concurrency == 0 {
+            return Err(ConfigError::Invalid(String::from(
+                "limits.concurrency must be at least 1",
+            )));
+        }
+        if self.limits.budget_window == 0 {
+            return Err(ConfigError::Invalid(String::from(
+                "limits.budget_window must be at least 1",
+            )));
+        }
+        if self.storage.path.as_os_str().is_empty() {
+            return Err(ConfigError::Invalid(String::from(
+                "storage.path must not be empty",
+            )));
+        }
+        if self.logging.level.is_some() && self.logging.level_filter().is_none() {
+            return Err(ConfigError::Invalid(format!(
+                "logging.level '{}' is not one of off, error, warn, info, debug or trace",
+                self.logging.level.clone().unwrap_or_default()
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Configuration shared between commands that can be reloaded at runtime.
+#[derive(Clone)]
+pub struct SharedConfig {
+    path: Option<PathBuf>,
+    config: Arc<RwLock<Arc<Config>>>,
+}
+
+impl SharedConfig {
+    pub fn load(path: Option<PathBuf>) -> Result<SharedConfig, ConfigError> {
+        let config = Config::load(path.as_deref())?;
+        Ok(SharedConfig {
+            path,
+            config: Arc::new(RwLock::new(Arc::new(config))),
+        })
+    }
+
+    /// Returns the current configuration.
+    pub fn get(&self) -> Arc<Config> {
+        self.config.read().unwrap().clone()
+    }
+
+    /// Reads the configuration file again, keeping the current configuration
+    /// if the new one is invalid. Returns the new configuration.
+    pub fn reload(&self) -> Result<Arc<Config>, ConfigError> {
+        let config = Arc::new(Config::load(self.path.as_deref())?);
+        *self.config.write().unwrap() = config.clone();
+        Ok(config)
+    }
+}
@@ -0,0 +1,195 @@
+use crate::storage::{now, Storage};
+
+use poise::serenity_prelude::ChannelId;
+
+use rusqlite::{params, types::Type, Connection};
+
+use serde::{Deserialize, Serialize};
+
+use std::time::Duration;
+
+/// Author of a chat message as understood by Ollama's `/api/chat`.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    System,
+    User,
+    Assistant,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Message {
+    pub role: Role,
+    pub content: String,
+    /// Base64 encoded images for vision models, never stored.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub images: Vec<String>,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::System => "system",
+            Role::User => "user",
+            Role::Assistant => "assistant",
+        }
+    }
+
+    pub fn parse(role: &str) -> Option<Role> {
+        match role {
+            "system" => Some(Role::System),
+            "user" => Some(Role::User),
+            "assistant" => Some(Role::Assistant),
+            _ => None,
+        }
+    }
+}
+
+impl Message {
+    pub fn new(role: Role, content: impl Into<String>) -> Self {
+        Message {
+            role,
+            content: content.into(),
+            images: Vec::new(),
+        }
+    }
+
+    pub fn with_images(mut self, images: Vec<String>) -> Self {
+        self.images = images;
+        self
+    }
+}
+
+/// Per-channel conversation memory sent back to Ollama as message history.
+///
+/// Threads have their own `ChannelId`, so every thread keeps a separate
+/// conversation. Conversations idle for longer than the `timeout` given to
+/// each call are dropped, and only the latest `max_turns` prompts and answers
+/// are kept.
+#[derive(Clone)]
+pub struct Conversations {
+    storage: Storage,
+}
+
+impl Conversations {
+    pub fn new(storage: Storage) -> Self {
+        Conversations { storage }
+    }
+
+    /// Stores a prompt and the answer given to it, forgetting the oldest
+    /// ones beyond `max_turns`.
+    pub fn record(
+        &self,
+        channel_id: ChannelId,
+        prompt: &str,
+        answer: &str,
+        timeout: Duration,
+        max_turns: Option<usize>,
+    ) -> rusqlite::Result<()> {
+        let mut connection = self.storage.connection();
+        let transaction = connection.transaction()?;
+        expire(&transaction, channel_id, timeout)?;
+        transaction.execute(
+            "INSERT INTO conversations (channel_id, last_active) VALUES (?1, ?2)
+             ON CONFLICT (channel_id) DO UPDATE SET last_active = excluded.last_active",
+            params![channel_id.get(), now()],
+        )?;
+        for message in [
+            Message::new(Role::User, prompt),
+            Message::new(Role::Assistant, answer),
+        ] {
+            transaction.execute(
+                "INSERT INTO conversation_messages (channel_id, role, content)
+                 VALUES (?1, ?2, ?3)",
+                params![channel_id.get(), message.role.as_str(), message.content],
+            )?;
+        }
+        if let Some(max_turns) = max_turns {
+            transaction.execute(
+                "DELETE FROM conversation_messages WHERE channel_id = ?1 AND id NOT IN (
+                     SELECT id FROM conversation_messages WHERE channel_id = ?1
+                     ORDER BY id DESC LIMIT ?2)",
+                params![channel_id.get(), max_turns * 2],
+            )?;
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    /// Replaces the latest answer `previous` with `answer`, for answers that
+    /// were regenerated or continued. Returns false when `previous` is no
+    /// longer part of the conversation.
+    pub fn revise(
+        &self,
+        channel_id: ChannelId,
+        previous: &str,
+        answer: &str,
+        timeout: Duration,
+    ) -> rusqlite::Result<bool> {
+        let mut connection = self.storage.connection();
+        let transaction = connection.transaction()?;
+        expire(&transaction, channel_id, timeout)?;
+        let updated = transaction.execute(
+            "UPDATE conversation_messages SET content = ?4 WHERE id = (
+                 SELECT MAX(id) FROM conversation_messages
+                 WHERE channel_id = ?1 AND role = ?2 AND content = ?3)",
+            params![channel_id.get(), Role::Assistant.as_str(), previous, answer],
+        )?;
+        if updated > 0 {
+            transaction.execute(
+                "UPDATE conversations SET last_active = ?2 WHERE channel_id = ?1",
+                params![channel_id.get(), now()],
+            )?;
+        }
+        transaction.commit()?;
+        Ok(updated > 0)
+    }
+
+    /// Forgets the conversation in `channel_id`, returns whether there was one.
+    pub fn reset(&self, channel_id: ChannelId, timeout: Duration) -> rusqlite::Result<bool> {
+        let connection = self.storage.connection();
+        expire(&connection, channel_id, timeout)?;
+        let deleted = connection.execute(
+            "DELETE FROM conversations WHERE channel_id = ?1",
+            params![channel_id.get()],
+        )?;
+        Ok(deleted > 0)
+    }
+
+    /// Returns the messages of the current conversation, oldest first, or
+    /// nothing when there is no conversation or it has expired.
+    pub fn history(
+        &self,
+        channel_id: ChannelId,
+        timeout: Duration,
+    ) -> rusqlite::Result<Vec<Message>> {
+        let connection = self.storage.connection();
+        expire(&connection, channel_id, timeout)?;
+        let mut statement = connection.prepare(
+            "SELECT role, content FROM conversation_messages WHERE channel_id = ?1 ORDER BY id",
+        )?;
+        let messages = statement
+            .query_map(params![channel_id.get()], |row| {
+                let role: String = row.get(0)?;
+                let role = Role::parse(&role).ok_or_else(|| {
+                    let error = format!("unknown role '{role}'");
+                    rusqlite::Error::FromSqlConversionFailure(0, Type::Text, error.into())
+                })?;
+                Ok(Message::new(role, row.get::<_, String>(1)?))
+            })?
+            .collect::<Result<_, _>>()?;
+        Ok(messages)
+    }
+}
+
+fn expire(
+    connection: &Connection,
+    channel_id: ChannelId,
+    timeout: Duration,
+) -> rusqlite::Result<()> {
+    connection.execute(
+        "DELETE FROM conversations WHERE channel_id = ?1 AND ?2 - last_active >= ?3",
+        params![channel_id.get(), now(), timeout.as_millis() as i64],
+    )?;
+    Ok(())
+}
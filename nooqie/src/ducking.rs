@@ -0,0 +1,40 @@
+use poise::serenity_prelude::GuildId;
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+/// Spoken answers playing per guild, the music stays turned down while any
+/// of them is.
+#[derive(Clone, Default)]
+pub struct Ducking {
+    speaking: Arc<Mutex<HashMap<GuildId, usize>>>,
+}
+
+impl Ducking {
+    /// Counts a spoken answer starting in `guild_id`.
+    pub fn start(&self, guild_id: GuildId) {
+        *self.speaking.lock().unwrap().entry(guild_id).or_default() += 1;
+    }
+
+    /// Counts a spoken answer ending in `guild_id`, returns whether the last
+    /// one did.
+    pub fn end(&self, guild_id: GuildId) -> bool {
+        let mut speaking = self.speaking.lock().unwrap();
+        match speaking.get_mut(&guild_id) {
+            Some(count) if *count > 1 => {
+                *count -= 1;
+                false
+            }
+            _ => {
+                speaking.remove(&guild_id);
+                true
+            }
+        }
+    }
+
+    pub fn is_ducked(&self, guild_id: GuildId) -> bool {
+        self.speaking.lock().unwrap().contains_key(&guild_id)
+    }
+}
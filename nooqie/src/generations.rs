@@ -0,0 +1,95 @@
+use poise::serenity_prelude::{ChannelId, UserId};
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use tokio::sync::Notify;
+
+struct Generation {
+    channel_id: ChannelId,
+    user_id: UserId,
+    stop: Arc<Notify>,
+}
+
+#[derive(Default)]
+struct State {
+    running: HashMap<u64, Generation>,
+    next_id: u64,
+}
+
+/// Answers the LLM is generating right now, so they can be stopped.
+#[derive(Clone, Default)]
+pub struct Generations {
+    state: Arc<Mutex<State>>,
+}
+
+impl Generations {
+    /// Tracks a generation for `user_id` in `channel_id` until the returned
+    /// handle is dropped.
+    pub fn start(&self, channel_id: ChannelId, user_id: UserId) -> GenerationHandle {
+        let mut state = self.state.lock().unwrap();
+        state.next_id += 1;
+        let id = state.next_id;
+        let stop = Arc::new(Notify::new());
+        state.running.insert(
+            id,
+            Generation {
+                channel_id,
+                user_id,
+                stop: stop.clone(),
+            },
+        );
+        GenerationHandle {
+            id,
+            stop,
+            generations: self.clone(),
+        }
+    }
+
+    /// Stops the latest generation `user_id` started in `channel_id`,
+    /// returns whether there was one.
+    pub fn stop_latest(&self, channel_id: ChannelId, user_id: UserId) -> bool {
+        let state = self.state.lock().unwrap();
+        let latest = state
+            .running
+            .iter()
+            .filter(|(_, generation)| {
+                generation.channel_id == channel_id && generation.user_id == user_id
+            })
+            .max_by_key(|(id, _)| **id);
+        match latest {
+            Some((_, generation)) => {
+                generation.stop.notify_one();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// A running generation, it stops being tracked when dropped.
+pub struct GenerationHandle {
+    id: u64,
+    stop: Arc<Notify>,
+    generations: Generations,
+}
+
+impl GenerationHandle {
+    /// Completes once the generation is asked to stop.
+    pub async fn stopped(&self) {
+        self.stop.notified().await;
+    }
+}
+
+impl Drop for GenerationHandle {
+    fn drop(&mut self) {
+        self.generations
+            .state
+            .lock()
+            .unwrap()
+            .running
+            .remove(&self.id);
+    }
+}
@@ -0,0 +1,87 @@
+use poise::serenity_prelude::GuildId;
+
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tokio::task::JoinHandle;
+
+#[derive(Default)]
+struct IdleState {
+    timers: HashMap<GuildId, JoinHandle<()>>,
+    paused: HashSet<GuildId>,
+}
+
+/// Pending disconnects of guilds where the bot is idle or alone in voice,
+/// and the guilds whose playback was paused because nobody was listening.
+#[derive(Clone, Default)]
+pub struct IdleTimers {
+    state: Arc<Mutex<IdleState>>,
+}
+
+impl IdleTimers {
+    /// Runs `leave` after `timeout` unless cancelled before. A disconnect
+    /// that is already pending keeps its original deadline.
+    pub fn schedule<F>(&self, guild_id: GuildId, timeout: Duration, leave: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let mut state = self.state.lock().unwrap();
+        if state
+            .timers
+            .get(&guild_id)
+            .is_some_and(|timer| !timer.is_finished())
+        {
+            return;
+        }
+        let timers = self.clone();
+        let timer = tokio::spawn(async move {
+            tokio::time::sleep(timeout).await;
+            timers.forget(guild_id);
+            leave.await;
+        });
+        state.timers.insert(guild_id, timer);
+    }
+
+    /// Cancels the pending disconnect of `guild_id`, returns whether there was
+    /// one.
+    pub fn cancel(&self, guild_id: GuildId) -> bool {
+        match self.state.lock().unwrap().timers.remove(&guild_id) {
+            Some(timer) => {
+                timer.abort();
+                !timer.is_finished()
+            }
+            None => false,
+        }
+    }
+
+    pub fn is_scheduled(&self, guild_id: GuildId) -> bool {
+        self.state
+            .lock()
+            .unwrap()
+            .timers
+            .get(&guild_id)
+            .is_some_and(|timer| !timer.is_finished())
+    }
+
+    /// Remembers that playback in `guild_id` was paused for being alone.
+    pub fn mark_paused(&self, guild_id: GuildId) {
+        self.state.lock().unwrap().paused.insert(guild_id);
+    }
+
+    /// Returns whether playback in `guild_id` was paused for being alone,
+    /// forgetting it.
+    pub fn take_paused(&self, guild_id: GuildId) -> bool {
+        self.state.lock().unwrap().paused.remove(&guild_id)
+    }
+
+    /// Drops all state of `guild_id` once the bot left its voice channel.
+    pub fn forget(&self, guild_id: GuildId) {
+        let mut state = self.state.lock().unwrap();
+        state.timers.remove(&guild_id);
+        state.paused.remove(&guild_id);
+    }
+}
@@ -1,5 +1,211 @@
+use poise::serenity_prelude as serenity;
+
+use std::{fmt, time::Duration};
+
 pub mod commands;
+pub mod config;
+pub mod conversation;
+pub mod ducking;
+pub mod generations;
+pub mod idle;
+pub mod listen;
+pub mod models;
+pub mod persona;
+pub mod playlists;
+pub mod prefixes;
+pub mod presence;
+pub mod replies;
+pub mod scheduler;
+pub mod splitter;
+pub mod storage;
+pub mod tracks;
+pub mod tts;
+pub mod volumes;
 pub type Context<'a> = poise::Context<'a, Data, Error>;
-pub type Error = Box<dyn std::error::Error + Send + Sync>;
+pub type Error = NooqieError;
+
+#[derive(Clone)]
+pub struct Data {
+    pub config: config::SharedConfig,
+    pub conversations: conversation::Conversations,
+    pub ducking: ducking::Ducking,
+    pub generations: generations::Generations,
+    /// Client shared by the requests to the LLM backends and whisper.
+    pub http: reqwest::Client,
+    pub idle: idle::IdleTimers,
+    pub listening: listen::Sessions,
+    pub models: models::Models,
+    pub personas: persona::Personas,
+    pub playlists: playlists::Playlists,
+    pub prefixes: prefixes::Prefixes,
+    pub presence: presence::Presence,
+    pub replies: replies::Replies,
+    pub scheduler: scheduler::Scheduler,
+    pub storage: storage::Storage,
+    pub volumes: volumes::Volumes,
+}
+
+impl Data {
+    /// Returns the model selected in `guild_id`, or the default of its backend.
+    pub fn model(&self, guild_id: Option<serenity::GuildId>) -> rusqlite::Result<String> {
+        match self.models.get(guild_id)? {
+            Some(model) => Ok(model),
+            None => {
+                let config = self.config.get();
+                Ok(config
+                    .backend(guild_id)
+                    .model
+                    .unwrap_or_else(|| config.ollama.model.clone()))
+            }
+        }
+    }
+
+    /// Returns the command prefix of `guild_id`, or the configured default.
+    pub fn prefix(&self, guild_id: Option<serenity::GuildId>) -> rusqlite::Result<String> {
+        match self.prefixes.get(guild_id)? {
+            Some(prefix) => Ok(prefix),
+            None => Ok(self.config.get().bot.prefix.clone()),
+        }
+    }
+}
+
+/// Errors returned by commands, displayed to the user that ran the command.
+#[derive(Debug)]
+pub enum NooqieError {
+    NotInGuild,
+    NotInVoiceChannel,
+    NotConnected,
+    NoVoiceClient,
+    NothingPlaying,
+    NotListening,
+    NotRequester,
+    InvalidPosition(usize),
+    QueueFull(usize),
+    MissingArgument(&'static str),
+    InvalidArgument(String),
+    NoVision(String),
+    UnknownModel(String),
+    Cooldown(Duration),
+    BudgetSpent(Duration),
+    Ollama(String),
+    YtDlp(String),
+    Tts(String),
+    Transcribe(String),
+    Http(reqwest::Error),
+    Json(serde_json::Error),
+    Discord(serenity::Error),
+    Join(songbird::error::JoinError),
+    Storage(rusqlite::Error),
+}
+
+impl NooqieError {
+    /// Whether the error is caused by the bot rather than by the user.
+    pub fn is_internal(&self) -> bool {
+        matches!(
+            self,
+            NooqieError::NoVoiceClient
+                | NooqieError::Ollama(_)
+                | NooqieError::YtDlp(_)
+                | NooqieError::Tts(_)
+                | NooqieError::Transcribe(_)
+                | NooqieError::Http(_)
+                | NooqieError::Json(_)
+                | NooqieError::Discord(_)
+                | NooqieError::Join(_)
+                | NooqieError::Storage(_)
+        )
+    }
+}
+
+impl fmt::Display for NooqieError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NooqieError::NotInGuild => write!(f, "this command only works in a server"),
+            NooqieError::NotInVoiceChannel => write!(f, "you need to be in a voice channel"),
+            NooqieError::NotConnected => write!(f, "I'm not in a voice channel"),
+            NooqieError::NoVoiceClient => write!(f, "voice is not available right now"),
+            NooqieError::NothingPlaying => write!(f, "nothing is playing"),
+            NooqieError::NotListening => write!(f, "I'm not listening"),
+            NooqieError::NotRequester => {
+                write!(f, "only the requester or a DJ can change that track")
+            }
+            NooqieError::InvalidPosition(index) => {
+                write!(f, "there's no track at position {index}")
+            }
+            NooqieError::QueueFull(max) => write!(f, "the queue is full ({max} tracks)"),
+            NooqieError::MissingArgument(argument) => write!(f, "missing {argument}"),
+            NooqieError::InvalidArgument(reason) => write!(f, "{reason}"),
+            NooqieError::NoVision(model) => write!(f, "{model} can't see images"),
+            NooqieError::UnknownModel(model) => write!(f, "no model called `{model}`"),
+            NooqieError::Cooldown(wait) => {
+                write!(f, "slow down, you can ask again in {}s", wait.as_secs() + 1)
+            }
+            NooqieError::BudgetSpent(wait) => write!(
+                f,
+                "this server used up its LLM budget, try again in {} minutes",
+                wait.as_secs() / 60 + 1
+            ),
+            NooqieError::Ollama(error) => write!(f, "the LLM failed to answer: {error}"),
+            NooqieError::YtDlp(error) => write!(f, "I couldn't load that: {error}"),
+            NooqieError::Tts(error) => write!(f, "I lost my voice: {error}"),
+            NooqieError::Transcribe(error) => write!(f, "I couldn't understand that: {error}"),
+            NooqieError::Http(_) => write!(f, "I seem to have dropped my brain :brain:"),
+            NooqieError::Json(_) => write!(f, "I got an answer I couldn't understand"),
+            NooqieError::Discord(_) => write!(f, "Discord didn't accept my message"),
+            NooqieError::Join(_) => write!(f, "I couldn't join your voice channel"),
+            NooqieError::Storage(_) => write!(f, "I couldn't reach my memory"),
+        }
+    }
+}
+
+impl std::error::Error for NooqieError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            NooqieError::Http(error) => Some(error),
+            NooqieError::Json(error) => Some(error),
+            NooqieError::Discord(error) => Some(error),
+            NooqieError::Join(error) => Some(error),
+            NooqieError::Storage(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for NooqieError {
+    fn from(error: reqwest::Error) -> Self {
+        NooqieError::Http(error)
+    }
+}
+
+impl From<scheduler::Refusal> for NooqieError {
+    fn from(refusal: scheduler::Refusal) -> Self {
+        match refusal {
+            scheduler::Refusal::Cooldown(wait) => NooqieError::Cooldown(wait),
+            scheduler::Refusal::BudgetSpent(wait) => NooqieError::BudgetSpent(wait),
+        }
+    }
+}
+
+impl From<serde_json::Error> for NooqieError {
+    fn from(error: serde_json::Error) -> Self {
+        NooqieError::Json(error)
+    }
+}
+
+impl From<serenity::Error> for NooqieError {
+    fn from(error: serenity::Error) -> Self {
+        NooqieError::Discord(error)
+    }
+}
+
+impl From<songbird::error::JoinError> for NooqieError {
+    fn from(error: songbird::error::JoinError) -> Self {
+        NooqieError::Join(error)
+    }
+}
 
-pub struct Data {}
+impl From<rusqlite::Error> for NooqieError {
+    fn from(error: rusqlite::Error) -> Self {
+        NooqieError::Storage(error)
+    }
+}
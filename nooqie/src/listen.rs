@@ -0,0 +1,291 @@
+use crate::NooqieError;
+
+use poise::serenity_prelude::{ChannelId, GuildId, UserId};
+
+use reqwest::{multipart, Client};
+
+use serde::Deserialize;
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+/// Sample rate received voice is decoded at, what whisper expects.
+pub const SAMPLE_RATE: u32 = 16_000;
+
+/// Words that may come before the wake word, as in "hey nooqie".
+const GREETINGS: &[&str] = &["hey", "hi", "ok", "okay"];
+
+/// Root mean square of `samples`, how loud they are.
+pub fn rms(samples: &[i16]) -> f64 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    let sum: f64 = samples.iter().map(|&s| f64::from(s) * f64::from(s)).sum();
+    (sum / samples.len() as f64).sqrt()
+}
+
+fn samples(duration: Duration) -> usize {
+    (duration.as_secs_f64() * f64::from(SAMPLE_RATE)) as usize
+}
+
+#[derive(Default)]
+struct Speaker {
+    samples: Vec<i16>,
+    voiced: usize,
+    silent: usize,
+}
+
+/// Energy based voice activity detection, splitting the audio of every
+/// speaker into utterances that end after a stretch of silence.
+pub struct Utterances {
+    threshold: f64,
+    silence: usize,
+    min_speech: usize,
+    max_length: usize,
+    speakers: HashMap<u32, Speaker>,
+}
+
+impl Utterances {
+    /// Frames louder than `threshold` are speech, an utterance ends after
+    /// `silence` without speech or once it is `max_length` long, and is
+    /// dropped when it holds less than `min_speech` of speech.
+    pub fn new(
+        threshold: f64,
+        silence: Duration,
+        min_speech: Duration,
+        max_length: Duration,
+    ) -> Self {
+        Utterances {
+            threshold,
+            silence: samples(silence),
+            min_speech: samples(min_speech),
+            max_length: samples(max_length),
+            speakers: HashMap::new(),
+        }
+    }
+
+    /// Adds a decoded `frame` of `ssrc`, returns the utterance it finished.
+    pub fn push(&mut self, ssrc: u32, frame: &[i16]) -> Option<Vec<i16>> {
+        let speaker = self.speakers.entry(ssrc).or_default();
+        if rms(frame) >= self.threshold {
+            speaker.voiced += frame.len();
+            speaker.silent = 0;
+        } else if speaker.samples.is_empty() {
+            return None;
+        } else {
+            speaker.silent += frame.len();
+        }
+        speaker.samples.extend_from_slice(frame);
+
+        if speaker.silent >= self.silence || speaker.samples.len() >= self.max_length {
+            self.finish(ssrc)
+        } else {
+            None
+        }
+    }
+
+    /// Notes that `ssrc` sent nothing for `duration`, which can end its
+    /// utterance.
+    pub fn silence(&mut self, ssrc: u32, duration: Duration) -> Option<Vec<i16>> {
+        let speaker = self.speakers.get_mut(&ssrc)?;
+        if speaker.samples.is_empty() {
+            return None;
+        }
+        speaker.silent += samples(duration);
+        if speaker.silent >= self.silence {
+            self.finish(ssrc)
+        } else {
+            None
+        }
+    }
+
+    /// Drops whatever `ssrc` said so far.
+    pub fn forget(&mut self, ssrc: u32) {
+        self.speakers.remove(&ssrc);
+    }
+
+    fn finish(&mut self, ssrc: u32) -> Option<Vec<i16>> {
+        let speaker = self.speakers.remove(&ssrc)?;
+        (speaker.voiced >= self.min_speech).then_some(speaker.samples)
+    }
+}
+
+/// Encodes mono 16 bit `samples` at `SAMPLE_RATE` as a WAV file.
+pub fn wav(samples: &[i16]) -> Vec<u8> {
+    let data_length = (samples.len() * 2) as u32;
+    let mut wav = Vec::with_capacity(44 + samples.len() * 2);
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_length).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    wav.extend_from_slice(&(SAMPLE_RATE * 2).to_le_bytes());
+    wav.extend_from_slice(&2u16.to_le_bytes());
+    wav.extend_from_slice(&16u16.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_length.to_le_bytes());
+    for sample in samples {
+        wav.extend_from_slice(&sample.to_le_bytes());
+    }
+    wav
+}
+
+fn normalize(word: &str) -> String {
+    word.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Returns what was said after `wake_word`, or `None` when `transcript`
+/// doesn't start with it. Case, punctuation and a greeting before it are
+/// ignored.
+pub fn strip_wake_word(transcript: &str, wake_word: &str) -> Option<String> {
+    let wake: Vec<String> = wake_word.split_whitespace().map(normalize).collect();
+    if wake.is_empty() {
+        return None;
+    }
+    let words: Vec<&str> = transcript.split_whitespace().collect();
+    let starts_at = |start: usize| {
+        words.len() >= start + wake.len()
+            && words[start..start + wake.len()]
+                .iter()
+                .zip(&wake)
+                .all(|(word, wake)| normalize(word) == *wake)
+    };
+
+    let start = if starts_at(0) {
+        0
+    } else if words
+        .first()
+        .is_some_and(|word| GREETINGS.contains(&normalize(word).as_str()))
+        && starts_at(1)
+    {
+        1
+    } else {
+        return None;
+    };
+
+    let rest = words[start + wake.len()..].join(" ");
+    Some(
+        rest.trim_start_matches(|c: char| !c.is_alphanumeric())
+            .to_string(),
+    )
+}
+
+#[derive(Deserialize)]
+struct Transcription {
+    text: String,
+}
+
+/// Transcribes `wav` with the whisper server at `url`, which takes the
+/// OpenAI `/v1/audio/transcriptions` form like whisper.cpp's server does.
+pub async fn transcribe(
+    http: &Client,
+    url: &str,
+    model: &str,
+    wav: Vec<u8>,
+) -> Result<String, NooqieError> {
+    let file = multipart::Part::bytes(wav)
+        .file_name("speech.wav")
+        .mime_str("audio/wav")?;
+    let form = multipart::Form::new()
+        .part("file", file)
+        .text("model", model.to_string())
+        .text("response_format", "json");
+    let response = http
+        .post(url)
+        .multipart(form)
+        .send()
+        .await?
+        .error_for_status()
+        .map_err(|error| NooqieError::Transcribe(error.to_string()))?;
+    let transcription: Transcription = response.json().await?;
+    Ok(transcription.text.trim().to_string())
+}
+
+struct Session {
+    id: u64,
+    channel_id: ChannelId,
+    consented: HashSet<UserId>,
+}
+
+/// Guilds nooqie is listening in, with the text channel transcripts go to
+/// and the people that allowed being listened to.
+#[derive(Clone, Default)]
+pub struct Sessions {
+    sessions: Arc<Mutex<HashMap<GuildId, Session>>>,
+    next_id: Arc<Mutex<u64>>,
+}
+
+impl Sessions {
+    /// Starts listening in `guild_id`, posting to `channel_id`. Returns the
+    /// id of the session, or `None` when already listening there.
+    pub fn start(&self, guild_id: GuildId, channel_id: ChannelId) -> Option<u64> {
+        let mut sessions = self.sessions.lock().unwrap();
+        if sessions.contains_key(&guild_id) {
+            return None;
+        }
+        let id = {
+            let mut next_id = self.next_id.lock().unwrap();
+            *next_id += 1;
+            *next_id
+        };
+        sessions.insert(
+            guild_id,
+            Session {
+                id,
+                channel_id,
+                consented: HashSet::new(),
+            },
+        );
+        Some(id)
+    }
+
+    /// Stops listening in `guild_id`, returns whether it was listening.
+    pub fn stop(&self, guild_id: GuildId) -> bool {
+        self.sessions.lock().unwrap().remove(&guild_id).is_some()
+    }
+
+    pub fn is_active(&self, guild_id: GuildId) -> bool {
+        self.sessions.lock().unwrap().contains_key(&guild_id)
+    }
+
+    /// Text channel of session `id` in `guild_id`, `None` once it ended.
+    pub fn channel(&self, guild_id: GuildId, id: u64) -> Option<ChannelId> {
+        self.sessions
+            .lock()
+            .unwrap()
+            .get(&guild_id)
+            .filter(|session| session.id == id)
+            .map(|session| session.channel_id)
+    }
+
+    /// Records whether `user_id` allows being listened to in session `id`,
+    /// returns false when the session ended.
+    pub fn set_consent(&self, guild_id: GuildId, id: u64, user_id: UserId, allow: bool) -> bool {
+        let mut sessions = self.sessions.lock().unwrap();
+        let Some(session) = sessions.get_mut(&guild_id).filter(|s| s.id == id) else {
+            return false;
+        };
+        if allow {
+            session.consented.insert(user_id);
+        } else {
+            session.consented.remove(&user_id);
+        }
+        true
+    }
+
+    pub fn has_consented(&self, guild_id: GuildId, id: u64, user_id: UserId) -> bool {
+        self.sessions
+            .lock()
+            .unwrap()
+            .get(&guild_id)
+            .is_some_and(|session| session.id == id && session.consented.contains(&user_id))
+    }
+}
@@ -5,19 +5,26 @@ use env_logger::{Builder, Env};
 use log::{debug, error, info, warn, LevelFilter};
 
 use poise::serenity_prelude as serenity;
-use poise::serenity_prelude::{Client, GatewayIntents};
+use poise::serenity_prelude::{Client, Colour, CreateEmbed, GatewayIntents};
+use poise::CreateReply;
 
 use songbird::SerenityInit;
 
-use std::{env, sync::Arc, time::Duration};
+use std::{path::PathBuf, sync::Arc, time::Duration};
 
 mod commands;
 
-use crate::commands::{ollama::*, utils::*, voice::*};
+use crate::commands::{listen::*, model::*, ollama::*, persona::*, prefix::*, utils::*, voice::*};
 
 use reqwest::Client as HttpClient;
 
-use nooqie::{Context, Data, Error};
+use nooqie::{
+    config, config::SharedConfig, conversation, conversation::Conversations, ducking::Ducking,
+    generations, generations::Generations, idle, idle::IdleTimers, listen, listen::Sessions,
+    models::Models, persona::Personas, playlists::Playlists, prefixes::Prefixes, presence,
+    presence::Presence, replies, replies::Replies, scheduler, scheduler::Scheduler, splitter,
+    storage::Storage, tracks, tts, volumes::Volumes, Context, Data, Error, NooqieError,
+};
 
 #[derive(Parser, Debug)]
 #[command(about=crate_description!())]
@@ -25,13 +32,32 @@ use nooqie::{Context, Data, Error};
 struct CLArgs {
     #[arg(short, long, default_value = "none")]
     loglevel: String,
+    /// Path of the TOML configuration file
+    #[arg(short, long)]
+    config: Option<PathBuf>,
 }
 
 async fn on_error(error: poise::FrameworkError<'_, Data, Error>) {
     match error {
         poise::FrameworkError::Setup { error, .. } => panic!("Failed to start bot: {:?}", error),
         poise::FrameworkError::Command { error, ctx, .. } => {
-            error!("Error in command `{}`: {:?}", ctx.command().name, error,);
+            if error.is_internal() {
+                error!("Error in command `{}`: {:?}", ctx.command().name, error,);
+            } else {
+                warn!(
+                    "{}: `{}` failed: {}",
+                    ctx.author(),
+                    ctx.command().name,
+                    error
+                );
+            }
+            let embed = CreateEmbed::new()
+                .description(error.to_string())
+                .colour(Colour::RED);
+            let reply = CreateReply::default().embed(embed).ephemeral(true);
+            if let Err(e) = ctx.send(reply).await {
+                error!("Error while reporting error: {}", e)
+            }
         }
         poise::FrameworkError::GuildOnly { ctx, .. } => {
             warn!("{}: bot not in guild", ctx.author());
@@ -48,10 +74,10 @@ async fn on_error(error: poise::FrameworkError<'_, Data, Error>) {
 }
 
 async fn event_handler(
-    _ctx: &serenity::Context,
+    ctx: &serenity::Context,
     event: &serenity::FullEvent,
     _framework: poise::FrameworkContext<'_, Data, Error>,
-    _data: &Data,
+    data: &Data,
 ) -> Result<(), Error> {
     match event {
         serenity::FullEvent::Ready { data_about_bot, .. } => {
@@ -60,11 +86,107 @@ async fn event_handler(
         serenity::FullEvent::ShardsReady { total_shards } => {
             info!("{} shards", total_shards);
         }
+        serenity::FullEvent::InteractionCreate {
+            interaction: serenity::Interaction::Component(press),
+        } => {
+            if let Err(error) = reply_button(ctx, data, press).await {
+                if error.is_internal() {
+                    error!(
+                        "Error handling button `{}`: {:?}",
+                        press.data.custom_id, error
+                    );
+                } else {
+                    warn!(
+                        "{}: `{}` failed: {}",
+                        press.user, press.data.custom_id, error
+                    );
+                }
+                let embed = CreateEmbed::new()
+                    .description(error.to_string())
+                    .colour(Colour::RED);
+                let response = serenity::CreateInteractionResponse::Message(
+                    serenity::CreateInteractionResponseMessage::new()
+                        .embed(embed.clone())
+                        .ephemeral(true),
+                );
+                // Fails when the press was already acknowledged.
+                if press.create_response(ctx, response).await.is_err() {
+                    let followup = serenity::CreateInteractionResponseFollowup::new()
+                        .embed(embed)
+                        .ephemeral(true);
+                    if let Err(e) = press.create_followup(ctx, followup).await {
+                        error!("Error while reporting error: {}", e)
+                    }
+                }
+            }
+        }
+        serenity::FullEvent::VoiceStateUpdate { new, .. } => {
+            if let Some(guild_id) = new.guild_id {
+                if new.user_id == ctx.cache.current_user().id
+                    && new.channel_id.is_none()
+                    && data.listening.stop(guild_id)
+                {
+                    info!("{}: stopped listening after leaving voice", guild_id);
+                }
+                let timeout = data.config.get().voice.idle_timeout();
+                check_idle(ctx, &data.idle, &data.listening, timeout, guild_id, None).await;
+            }
+        }
         _ => {}
     }
     Ok(())
 }
 
+/// Resolves the prefix of the guild the message was sent in. Mentioning the
+/// bot works as well through `mention_as_prefix`.
+async fn dynamic_prefix(
+    ctx: poise::PartialContext<'_, Data, Error>,
+) -> Result<Option<String>, Error> {
+    match ctx.data.prefix(ctx.guild_id) {
+        Ok(prefix) => Ok(Some(prefix)),
+        Err(error) => {
+            error!("failed to get prefix of {:?}: {error}", ctx.guild_id);
+            Ok(Some(ctx.data.config.get().bot.prefix.clone()))
+        }
+    }
+}
+
+/// Reloads the configuration whenever SIGHUP is received, the gateway
+/// connection is kept so the bot token can't be changed this way.
+#[cfg(unix)]
+async fn reload_on_hangup(config: SharedConfig, reload_loglevel: bool) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangup = match signal(SignalKind::hangup()) {
+        Ok(hangup) => hangup,
+        Err(error) => {
+            error!("failed to listen for SIGHUP: {error}");
+            return;
+        }
+    };
+
+    while hangup.recv().await.is_some() {
+        info!("Received SIGHUP, reloading configuration.");
+        let token = config.get().bot.token.clone();
+        match config.reload() {
+            Ok(reloaded) => {
+                if reload_loglevel {
+                    if let Some(level) = reloaded.logging.level_filter() {
+                        log::set_max_level(level);
+                    }
+                }
+                if reloaded.bot.token != token {
+                    warn!("bot.token changed, restart to apply");
+                }
+                info!("configuration reloaded");
+            }
+            Err(error) => {
+                error!("failed to reload configuration, keeping the current one: {error}")
+            }
+        }
+    }
+}
+
 #[tokio::main]
 async fn main() {
     let clargs: CLArgs = CLArgs::parse();
@@ -72,9 +194,13 @@ async fn main() {
         .install_default()
         .expect("Failed to install rustls crypto provider");
     let _ = dotenvy::dotenv();
-    let token: String =
-        env::var("DISCORD_TOKEN").expect("'DISCORD_TOKEN' environment variable not set");
+    let config = match SharedConfig::load(clargs.config.clone()) {
+        Ok(config) => config,
+        Err(error) => panic!("Failed to load configuration: {error}"),
+    };
+    let token: String = config.get().bot.token.clone();
 
+    let mut reload_loglevel = false;
     if clargs.loglevel != "none" {
         let mut builder: Builder = Builder::new();
 
@@ -96,6 +222,12 @@ async fn main() {
             }
             &_ => {}
         }
+    } else if let Some(level) = config.get().logging.level_filter() {
+        Builder::new()
+            .filter_module("nooqie", LevelFilter::Trace)
+            .init();
+        log::set_max_level(level);
+        reload_loglevel = true;
     } else {
         let env: Env<'_> = Env::new();
         env_logger::init_from_env(env);
@@ -109,17 +241,18 @@ async fn main() {
         | GatewayIntents::GUILDS
         | GatewayIntents::GUILD_VOICE_STATES;
 
-    let prefix: String = match env::var("NOOQIE_PREFIX") {
-        Ok(prefix) => prefix,
-        Err(_error) => String::from("!"),
-    };
-
     let options = poise::FrameworkOptions {
         commands: vec![
             help(),
             ping(),
             pong(),
             llm(),
+            chat(),
+            ask(),
+            listen(),
+            model(),
+            persona(),
+            prefix(),
             join(),
             leave(),
             play(),
@@ -128,9 +261,22 @@ async fn main() {
             skip(),
             clear(),
             loop_track(),
+            queue(),
+            remove(),
+            move_track(),
+            shuffle(),
+            skipto(),
+            playnext(),
+            seek(),
+            forward(),
+            rewind(),
+            volume(),
+            nowplaying(),
+            sounds(),
         ],
         prefix_options: poise::PrefixFrameworkOptions {
-            prefix: Some(prefix),
+            dynamic_prefix: Some(|ctx| Box::pin(dynamic_prefix(ctx))),
+            mention_as_prefix: true,
             edit_tracker: Some(Arc::new(poise::EditTracker::for_timespan(
                 Duration::from_secs(3600),
             ))),
@@ -154,11 +300,39 @@ async fn main() {
         ..Default::default()
     };
 
+    let storage_path = config.get().storage.path.clone();
+    let storage = match Storage::open(&storage_path) {
+        Ok(storage) => storage,
+        Err(error) => panic!(
+            "Failed to open database '{}': {error}",
+            storage_path.display()
+        ),
+    };
+    info!("using database '{}'", storage_path.display());
+
+    let data_config = config.clone();
     let framework = poise::Framework::builder()
         .setup(move |ctx, _ready, framework| {
             Box::pin(async move {
                 poise::builtins::register_globally(ctx, &framework.options().commands).await?;
-                Ok(Data {})
+                Ok(Data {
+                    config: data_config,
+                    conversations: Conversations::new(storage.clone()),
+                    ducking: Ducking::default(),
+                    generations: Generations::default(),
+                    http: HttpClient::new(),
+                    idle: IdleTimers::default(),
+                    listening: Sessions::default(),
+                    models: Models::new(storage.clone()),
+                    personas: Personas::new(storage.clone()),
+                    playlists: Playlists::new(storage.clone()),
+                    prefixes: Prefixes::new(storage.clone()),
+                    presence: Presence::default(),
+                    replies: Replies::new(storage.clone()),
+                    scheduler: Scheduler::default(),
+                    volumes: Volumes::new(storage.clone()),
+                    storage,
+                })
             })
         })
         .options(options)
@@ -178,6 +352,9 @@ async fn main() {
             .map_err(|why| error!("client ended: {:?}", why));
     });
 
+    #[cfg(unix)]
+    tokio::spawn(reload_on_hangup(config, reload_loglevel));
+
     let _signal_err = tokio::signal::ctrl_c().await;
     info!("Received Ctrl-C, shutting down.");
 }
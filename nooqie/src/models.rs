@@ -0,0 +1,43 @@
+use crate::storage::Storage;
+
+use poise::serenity_prelude::GuildId;
+
+use rusqlite::{params, OptionalExtension};
+
+/// Ollama model selected per guild.
+#[derive(Clone)]
+pub struct Models {
+    storage: Storage,
+}
+
+impl Models {
+    pub fn new(storage: Storage) -> Self {
+        Models { storage }
+    }
+
+    /// Returns the model selected in `guild_id`, if any.
+    pub fn get(&self, guild_id: Option<GuildId>) -> rusqlite::Result<Option<String>> {
+        let Some(guild_id) = guild_id else {
+            return Ok(None);
+        };
+        let model = self
+            .storage
+            .connection()
+            .query_row(
+                "SELECT model FROM guild_settings WHERE guild_id = ?1",
+                params![guild_id.get()],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(model.flatten())
+    }
+
+    pub fn set(&self, guild_id: GuildId, model: &str) -> rusqlite::Result<()> {
+        self.storage.connection().execute(
+            "INSERT INTO guild_settings (guild_id, model) VALUES (?1, ?2)
+             ON CONFLICT (guild_id) DO UPDATE SET model = excluded.model",
+            params![guild_id.get(), model],
+        )?;
+        Ok(())
+    }
+}
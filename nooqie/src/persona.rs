@@ -0,0 +1,138 @@
+use crate::storage::Storage;
+
+use poise::serenity_prelude::{ChannelId, GuildId};
+
+use rusqlite::{params, OptionalExtension};
+
+/// Named system prompts per guild, with an active persona for the guild that
+/// can be overridden per channel.
+#[derive(Clone)]
+pub struct Personas {
+    storage: Storage,
+}
+
+impl Personas {
+    pub fn new(storage: Storage) -> Self {
+        Personas { storage }
+    }
+
+    /// Creates or replaces the persona `name`.
+    pub fn set(&self, guild_id: GuildId, name: &str, prompt: &str) -> rusqlite::Result<()> {
+        self.storage.connection().execute(
+            "INSERT INTO personas (guild_id, name, prompt) VALUES (?1, ?2, ?3)
+             ON CONFLICT (guild_id, name) DO UPDATE SET prompt = excluded.prompt",
+            params![guild_id.get(), name.to_lowercase(), prompt],
+        )?;
+        Ok(())
+    }
+
+    /// Returns every persona of the guild as `(name, prompt)`, sorted by name.
+    pub fn list(&self, guild_id: GuildId) -> rusqlite::Result<Vec<(String, String)>> {
+        let connection = self.storage.connection();
+        let mut statement = connection
+            .prepare("SELECT name, prompt FROM personas WHERE guild_id = ?1 ORDER BY name")?;
+        let personas = statement
+            .query_map(params![guild_id.get()], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })?
+            .collect::<Result<_, _>>()?;
+        Ok(personas)
+    }
+
+    /// Activates `name` for the guild, or only for `channel_id` when given.
+    /// `None` deactivates the persona instead. Returns false if there is no
+    /// persona called `name`.
+    pub fn activate(
+        &self,
+        guild_id: GuildId,
+        channel_id: Option<ChannelId>,
+        name: Option<&str>,
+    ) -> rusqlite::Result<bool> {
+        let mut connection = self.storage.connection();
+        let transaction = connection.transaction()?;
+        let name = match name {
+            Some(name) => {
+                let name = name.to_lowercase();
+                let exists = transaction
+                    .query_row(
+                        "SELECT 1 FROM personas WHERE guild_id = ?1 AND name = ?2",
+                        params![guild_id.get(), name],
+                        |_| Ok(()),
+                    )
+                    .optional()?
+                    .is_some();
+                if !exists {
+                    return Ok(false);
+                }
+                Some(name)
+            }
+            None => None,
+        };
+        match (channel_id, name) {
+            (Some(channel_id), Some(name)) => transaction.execute(
+                "INSERT OR REPLACE INTO channel_personas (channel_id, guild_id, persona)
+                 VALUES (?1, ?2, ?3)",
+                params![channel_id.get(), guild_id.get(), name],
+            )?,
+            (Some(channel_id), None) => transaction.execute(
+                "DELETE FROM channel_personas WHERE channel_id = ?1",
+                params![channel_id.get()],
+            )?,
+            (None, name) => transaction.execute(
+                "INSERT INTO guild_settings (guild_id, persona) VALUES (?1, ?2)
+                 ON CONFLICT (guild_id) DO UPDATE SET persona = excluded.persona",
+                params![guild_id.get(), name],
+            )?,
+        };
+        transaction.commit()?;
+        Ok(true)
+    }
+
+    /// Deletes the persona `name` and deactivates it wherever it was active.
+    /// Returns false if there is no persona called `name`.
+    pub fn delete(&self, guild_id: GuildId, name: &str) -> rusqlite::Result<bool> {
+        let name = name.to_lowercase();
+        let mut connection = self.storage.connection();
+        let transaction = connection.transaction()?;
+        let deleted = transaction.execute(
+            "DELETE FROM personas WHERE guild_id = ?1 AND name = ?2",
+            params![guild_id.get(), name],
+        )?;
+        if deleted == 0 {
+            return Ok(false);
+        }
+        transaction.execute(
+            "UPDATE guild_settings SET persona = NULL WHERE guild_id = ?1 AND persona = ?2",
+            params![guild_id.get(), name],
+        )?;
+        transaction.execute(
+            "DELETE FROM channel_personas WHERE guild_id = ?1 AND persona = ?2",
+            params![guild_id.get(), name],
+        )?;
+        transaction.commit()?;
+        Ok(true)
+    }
+
+    /// Returns the name and system prompt of the persona active in
+    /// `channel_id`, the channel override taking precedence over the guild.
+    pub fn active(
+        &self,
+        guild_id: GuildId,
+        channel_id: ChannelId,
+    ) -> rusqlite::Result<Option<(String, String)>> {
+        let persona = self
+            .storage
+            .connection()
+            .query_row(
+                "SELECT personas.name, personas.prompt FROM personas
+                 WHERE personas.guild_id = ?1 AND personas.name = COALESCE(
+                     (SELECT persona FROM channel_personas WHERE guild_id = ?1 AND channel_id = ?2),
+                     (SELECT persona FROM guild_settings WHERE guild_id = ?1)
+                 )",
+                params![guild_id.get(), channel_id.get()],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        Ok(persona)
+    }
+}
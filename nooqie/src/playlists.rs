@@ -0,0 +1,112 @@
+use crate::storage::Storage;
+
+use poise::serenity_prelude::{GuildId, UserId};
+
+use rusqlite::{params, OptionalExtension};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlaylistTrack {
+    pub url: String,
+    pub title: Option<String>,
+}
+
+/// Named track lists saved per guild.
+#[derive(Clone)]
+pub struct Playlists {
+    storage: Storage,
+}
+
+impl Playlists {
+    pub fn new(storage: Storage) -> Self {
+        Playlists { storage }
+    }
+
+    /// Saves `tracks` as the playlist `name`, replacing any playlist with the
+    /// same name.
+    pub fn save(
+        &self,
+        guild_id: GuildId,
+        owner_id: UserId,
+        name: &str,
+        tracks: &[PlaylistTrack],
+    ) -> rusqlite::Result<()> {
+        let mut connection = self.storage.connection();
+        let transaction = connection.transaction()?;
+        transaction.execute(
+            "DELETE FROM playlists WHERE guild_id = ?1 AND name = ?2",
+            params![guild_id.get(), name.to_lowercase()],
+        )?;
+        transaction.execute(
+            "INSERT INTO playlists (guild_id, name, owner_id) VALUES (?1, ?2, ?3)",
+            params![guild_id.get(), name.to_lowercase(), owner_id.get()],
+        )?;
+        let playlist_id = transaction.last_insert_rowid();
+        for (position, track) in tracks.iter().enumerate() {
+            transaction.execute(
+                "INSERT INTO playlist_tracks (playlist_id, position, url, title)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![playlist_id, position, track.url, track.title],
+            )?;
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    /// Returns the tracks of the playlist `name` in order, or `None` if there
+    /// is no such playlist.
+    pub fn load(
+        &self,
+        guild_id: GuildId,
+        name: &str,
+    ) -> rusqlite::Result<Option<Vec<PlaylistTrack>>> {
+        let connection = self.storage.connection();
+        let playlist_id: Option<i64> = connection
+            .query_row(
+                "SELECT id FROM playlists WHERE guild_id = ?1 AND name = ?2",
+                params![guild_id.get(), name.to_lowercase()],
+                |row| row.get(0),
+            )
+            .optional()?;
+        let Some(playlist_id) = playlist_id else {
+            return Ok(None);
+        };
+        let mut statement = connection.prepare(
+            "SELECT url, title FROM playlist_tracks WHERE playlist_id = ?1 ORDER BY position",
+        )?;
+        let tracks = statement
+            .query_map(params![playlist_id], |row| {
+                Ok(PlaylistTrack {
+                    url: row.get(0)?,
+                    title: row.get(1)?,
+                })
+            })?
+            .collect::<Result<_, _>>()?;
+        Ok(Some(tracks))
+    }
+
+    /// Returns the names of the guild's playlists with their track count,
+    /// sorted by name.
+    pub fn list(&self, guild_id: GuildId) -> rusqlite::Result<Vec<(String, usize)>> {
+        let connection = self.storage.connection();
+        let mut statement = connection.prepare(
+            "SELECT playlists.name, COUNT(playlist_tracks.position) FROM playlists
+             LEFT JOIN playlist_tracks ON playlist_tracks.playlist_id = playlists.id
+             WHERE playlists.guild_id = ?1 GROUP BY playlists.id ORDER BY playlists.name",
+        )?;
+        let playlists = statement
+            .query_map(params![guild_id.get()], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })?
+            .collect::<Result<_, _>>()?;
+        Ok(playlists)
+    }
+
+    /// Deletes the playlist `name`, returns false if there was none.
+    pub fn delete(&self, guild_id: GuildId, name: &str) -> rusqlite::Result<bool> {
+        let deleted = self.storage.connection().execute(
+            "DELETE FROM playlists WHERE guild_id = ?1 AND name = ?2",
+            params![guild_id.get(), name.to_lowercase()],
+        )?;
+        Ok(deleted > 0)
+    }
+}
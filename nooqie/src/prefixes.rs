@@ -0,0 +1,44 @@
+use crate::storage::Storage;
+
+use poise::serenity_prelude::GuildId;
+
+use rusqlite::{params, OptionalExtension};
+
+/// Command prefix chosen per guild.
+#[derive(Clone)]
+pub struct Prefixes {
+    storage: Storage,
+}
+
+impl Prefixes {
+    pub fn new(storage: Storage) -> Self {
+        Prefixes { storage }
+    }
+
+    /// Returns the prefix set in `guild_id`, if any.
+    pub fn get(&self, guild_id: Option<GuildId>) -> rusqlite::Result<Option<String>> {
+        let Some(guild_id) = guild_id else {
+            return Ok(None);
+        };
+        let prefix = self
+            .storage
+            .connection()
+            .query_row(
+                "SELECT prefix FROM guild_settings WHERE guild_id = ?1",
+                params![guild_id.get()],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(prefix.flatten())
+    }
+
+    /// Sets the prefix of `guild_id`, `None` goes back to the configured one.
+    pub fn set(&self, guild_id: GuildId, prefix: Option<&str>) -> rusqlite::Result<()> {
+        self.storage.connection().execute(
+            "INSERT INTO guild_settings (guild_id, prefix) VALUES (?1, ?2)
+             ON CONFLICT (guild_id) DO UPDATE SET prefix = excluded.prefix",
+            params![guild_id.get(), prefix],
+        )?;
+        Ok(())
+    }
+}
@@ -0,0 +1,73 @@
+use poise::serenity_prelude::{self as serenity, ActivityData, GuildId, OnlineStatus};
+
+use std::sync::{Arc, Mutex};
+
+#[derive(Default)]
+struct State {
+    thinking: usize,
+    /// Track playing in each guild, the one that started last at the end.
+    playing: Vec<(GuildId, String)>,
+}
+
+/// The bot's presence: "thinking..." while answers are being generated,
+/// otherwise the track that started last in any guild still playing.
+#[derive(Clone, Default)]
+pub struct Presence {
+    state: Arc<Mutex<State>>,
+}
+
+impl Presence {
+    /// Shows `title` as playing in `guild_id`, or what another guild plays
+    /// once playback there ended, as soon as no answers are being generated.
+    pub fn playing(&self, ctx: &serenity::Context, guild_id: GuildId, title: Option<String>) {
+        let mut state = self.state.lock().unwrap();
+        state
+            .playing
+            .retain(|(playing_in, _)| *playing_in != guild_id);
+        if let Some(title) = title {
+            state.playing.push((guild_id, title));
+        }
+        apply(ctx, &state);
+    }
+
+    /// Shows "thinking..." until the returned guard is dropped.
+    pub fn thinking(&self, ctx: &serenity::Context) -> Thinking {
+        let mut state = self.state.lock().unwrap();
+        state.thinking += 1;
+        apply(ctx, &state);
+        Thinking {
+            presence: self.clone(),
+            ctx: ctx.clone(),
+        }
+    }
+}
+
+/// An answer being generated, the presence goes back to what is playing
+/// once the last one is dropped.
+pub struct Thinking {
+    presence: Presence,
+    ctx: serenity::Context,
+}
+
+impl Drop for Thinking {
+    fn drop(&mut self) {
+        let mut state = self.presence.state.lock().unwrap();
+        state.thinking -= 1;
+        apply(&self.ctx, &state);
+    }
+}
+
+fn apply(ctx: &serenity::Context, state: &State) {
+    if state.thinking > 0 {
+        ctx.set_presence(
+            Some(ActivityData::custom("thinking...")),
+            OnlineStatus::DoNotDisturb,
+        );
+    } else {
+        let activity = state
+            .playing
+            .last()
+            .map(|(_, title)| ActivityData::listening(title));
+        ctx.set_presence(activity, OnlineStatus::Online);
+    }
+}
@@ -0,0 +1,180 @@
+use crate::conversation::Message;
+use crate::storage::{now, Storage};
+
+use poise::serenity_prelude::{ChannelId, GuildId, MessageId, UserId};
+
+use rusqlite::{params, types::Type, OptionalExtension};
+
+use serde::Serialize;
+
+use std::time::Duration;
+
+/// Replies nobody rated are forgotten after this long, their buttons stop
+/// working then.
+const RETENTION: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// An answer of the LLM and what it was asked, so the buttons under it can
+/// regenerate or continue it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Reply {
+    pub guild_id: Option<GuildId>,
+    pub channel_id: ChannelId,
+    pub model: String,
+    /// What was asked, as recorded in the conversation.
+    pub prompt: String,
+    /// Messages sent to the model ending with the prompt, without the
+    /// persona's system prompt and without images.
+    pub messages: Vec<Message>,
+    pub answer: String,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Rating {
+    Up,
+    Down,
+}
+
+impl Rating {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Rating::Up => "up",
+            Rating::Down => "down",
+        }
+    }
+
+    pub fn parse(rating: &str) -> Option<Rating> {
+        match rating {
+            "up" => Some(Rating::Up),
+            "down" => Some(Rating::Down),
+            _ => None,
+        }
+    }
+}
+
+/// A rated reply as exported for prompt tuning, one JSON line each.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct Feedback {
+    pub model: String,
+    pub messages: Vec<Message>,
+    pub answer: String,
+    pub rating: Rating,
+    /// Milliseconds since the unix epoch.
+    pub rated_at: i64,
+}
+
+/// Replies of the LLM by the Discord message holding their buttons, and the
+/// ratings people gave them.
+#[derive(Clone)]
+pub struct Replies {
+    storage: Storage,
+}
+
+impl Replies {
+    pub fn new(storage: Storage) -> Self {
+        Replies { storage }
+    }
+
+    /// Stores `reply` as the one shown in `message_id`.
+    pub fn record(&self, message_id: MessageId, reply: &Reply) -> rusqlite::Result<()> {
+        let messages = serde_json::to_string(&reply.messages)
+            .map_err(|error| rusqlite::Error::ToSqlConversionFailure(error.into()))?;
+        let connection = self.storage.connection();
+        connection.execute(
+            "DELETE FROM replies WHERE ?1 - created >= ?2
+             AND NOT EXISTS (SELECT 1 FROM feedback WHERE feedback.message_id = replies.message_id)",
+            params![now(), RETENTION.as_millis() as i64],
+        )?;
+        connection.execute(
+            "INSERT OR REPLACE INTO replies
+             (message_id, guild_id, channel_id, model, prompt, messages, answer, created)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                message_id.get(),
+                reply.guild_id.map(GuildId::get),
+                reply.channel_id.get(),
+                reply.model,
+                reply.prompt,
+                messages,
+                reply.answer,
+                now()
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Returns the reply shown in `message_id`, if it is still known.
+    pub fn get(&self, message_id: MessageId) -> rusqlite::Result<Option<Reply>> {
+        self.storage
+            .connection()
+            .query_row(
+                "SELECT guild_id, channel_id, model, prompt, messages, answer
+                 FROM replies WHERE message_id = ?1",
+                params![message_id.get()],
+                |row| {
+                    let messages: String = row.get(4)?;
+                    let messages = serde_json::from_str(&messages).map_err(|error| {
+                        rusqlite::Error::FromSqlConversionFailure(4, Type::Text, error.into())
+                    })?;
+                    Ok(Reply {
+                        guild_id: row.get::<_, Option<u64>>(0)?.map(GuildId::new),
+                        channel_id: ChannelId::new(row.get(1)?),
+                        model: row.get(2)?,
+                        prompt: row.get(3)?,
+                        messages,
+                        answer: row.get(5)?,
+                    })
+                },
+            )
+            .optional()
+    }
+
+    /// Records `user_id` rating the reply in `message_id`, replacing their
+    /// earlier rating. Returns false when the reply is not known.
+    pub fn rate(
+        &self,
+        message_id: MessageId,
+        user_id: UserId,
+        rating: Rating,
+    ) -> rusqlite::Result<bool> {
+        let inserted = self.storage.connection().execute(
+            "INSERT INTO feedback (message_id, user_id, rating, rated)
+             SELECT message_id, ?2, ?3, ?4 FROM replies WHERE message_id = ?1
+             ON CONFLICT (message_id, user_id) DO UPDATE
+             SET rating = excluded.rating, rated = excluded.rated",
+            params![message_id.get(), user_id.get(), rating.as_str(), now()],
+        )?;
+        Ok(inserted > 0)
+    }
+
+    /// Returns the ratings given in `guild_id`, oldest first.
+    pub fn feedback(&self, guild_id: GuildId) -> rusqlite::Result<Vec<Feedback>> {
+        let connection = self.storage.connection();
+        let mut statement = connection.prepare(
+            "SELECT replies.model, replies.messages, replies.answer, feedback.rating, feedback.rated
+             FROM feedback JOIN replies ON replies.message_id = feedback.message_id
+             WHERE replies.guild_id = ?1 ORDER BY feedback.rated",
+        )?;
+        let feedback = statement
+            .query_map(params![guild_id.get()], |row| {
+                let messages: String = row.get(1)?;
+                let messages = serde_json::from_str(&messages).map_err(|error| {
+                    rusqlite::Error::FromSqlConversionFailure(1, Type::Text, error.into())
+                })?;
+                let rating: String = row.get(3)?;
+                let rating = Rating::parse(&rating).ok_or_else(|| {
+                    let error = format!("unknown rating '{rating}'");
+                    rusqlite::Error::FromSqlConversionFailure(3, Type::Text, error.into())
+                })?;
+                Ok(Feedback {
+                    model: row.get(0)?,
+                    messages,
+                    answer: row.get(2)?,
+                    rating,
+                    rated_at: row.get(4)?,
+                })
+            })?
+            .collect::<Result<_, _>>()?;
+        Ok(feedback)
+    }
+}
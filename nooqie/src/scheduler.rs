@@ -0,0 +1,176 @@
+use crate::config::LimitsConfig;
+
+use poise::serenity_prelude::{GuildId, UserId};
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use tokio::sync::Notify;
+
+/// Why a request was not admitted, with how long until it would be.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Refusal {
+    Cooldown(Duration),
+    BudgetSpent(Duration),
+}
+
+#[derive(Default)]
+struct State {
+    running: usize,
+    queue: VecDeque<u64>,
+    next_id: u64,
+    last_request: HashMap<UserId, Instant>,
+    spent: HashMap<GuildId, VecDeque<(Instant, u64)>>,
+}
+
+/// Decides who gets to talk to the LLM: every user has to wait a cooldown
+/// between requests, every guild has a budget of tokens per window, and
+/// admitted requests wait in line until fewer than the concurrency limit
+/// are running.
+#[derive(Clone, Default)]
+pub struct Scheduler {
+    state: Arc<Mutex<State>>,
+    notify: Arc<Notify>,
+}
+
+impl Scheduler {
+    /// Admits a request of `user_id` in `guild_id` and puts it at the end of
+    /// the line, unless the user is cooling down or the guild spent its
+    /// budget.
+    pub fn admit(
+        &self,
+        user_id: UserId,
+        guild_id: Option<GuildId>,
+        limits: &LimitsConfig,
+    ) -> Result<Ticket, Refusal> {
+        let now = Instant::now();
+        let cooldown = limits.cooldown();
+        let window = limits.budget_window();
+        let mut state = self.state.lock().unwrap();
+
+        state
+            .last_request
+            .retain(|_, last| now.duration_since(*last) < cooldown);
+        if let Some(last) = state.last_request.get(&user_id) {
+            return Err(Refusal::Cooldown(cooldown - now.duration_since(*last)));
+        }
+
+        if let (Some(guild_id), Some(budget)) = (guild_id, limits.guild_budget()) {
+            if let Some(spent) = state.spent.get_mut(&guild_id) {
+                while spent
+                    .front()
+                    .is_some_and(|(at, _)| now.duration_since(*at) >= window)
+                {
+                    spent.pop_front();
+                }
+                if spent.iter().map(|(_, tokens)| tokens).sum::<u64>() >= budget {
+                    let (oldest, _) = spent[0];
+                    return Err(Refusal::BudgetSpent(window - now.duration_since(oldest)));
+                }
+            }
+        }
+
+        if !cooldown.is_zero() {
+            state.last_request.insert(user_id, now);
+        }
+        state.next_id += 1;
+        let id = state.next_id;
+        state.queue.push_back(id);
+        Ok(Ticket {
+            id,
+            scheduler: self.clone(),
+        })
+    }
+
+    /// Counts `tokens` generated for `guild_id` against its budget.
+    pub fn record(&self, guild_id: GuildId, tokens: u64) {
+        if tokens == 0 {
+            return;
+        }
+        self.state
+            .lock()
+            .unwrap()
+            .spent
+            .entry(guild_id)
+            .or_default()
+            .push_back((Instant::now(), tokens));
+    }
+
+    /// Lets `user_id` ask again right away, for requests that failed before
+    /// the LLM answered.
+    pub fn refund(&self, user_id: UserId) {
+        self.state.lock().unwrap().last_request.remove(&user_id);
+    }
+}
+
+/// Place of an admitted request in line, it leaves the line when dropped.
+pub struct Ticket {
+    id: u64,
+    scheduler: Scheduler,
+}
+
+impl Ticket {
+    /// Position in line starting at 1, `None` once the request started.
+    pub fn position(&self) -> Option<usize> {
+        let state = self.scheduler.state.lock().unwrap();
+        state
+            .queue
+            .iter()
+            .position(|id| *id == self.id)
+            .map(|index| index + 1)
+    }
+
+    /// Starts the request if it is first in line and fewer than
+    /// `concurrency` requests are running.
+    pub fn try_start(&self, concurrency: usize) -> Option<Permit> {
+        let mut state = self.scheduler.state.lock().unwrap();
+        if state.queue.front() != Some(&self.id) || state.running >= concurrency {
+            return None;
+        }
+        state.queue.pop_front();
+        state.running += 1;
+        drop(state);
+        // The next in line may be able to start as well.
+        self.scheduler.notify.notify_waiters();
+        Some(Permit {
+            scheduler: self.scheduler.clone(),
+        })
+    }
+
+    /// Waits until the request can start.
+    pub async fn start(&self, concurrency: usize) -> Permit {
+        loop {
+            let notified = self.scheduler.notify.notified();
+            if let Some(permit) = self.try_start(concurrency) {
+                return permit;
+            }
+            notified.await;
+        }
+    }
+}
+
+impl Drop for Ticket {
+    fn drop(&mut self) {
+        let mut state = self.scheduler.state.lock().unwrap();
+        if let Some(index) = state.queue.iter().position(|id| *id == self.id) {
+            state.queue.remove(index);
+            drop(state);
+            self.scheduler.notify.notify_waiters();
+        }
+    }
+}
+
+/// A running request, the next in line may start once it is dropped.
+pub struct Permit {
+    scheduler: Scheduler,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        self.scheduler.state.lock().unwrap().running -= 1;
+        self.scheduler.notify.notify_waiters();
+    }
+}
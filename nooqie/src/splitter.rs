@@ -0,0 +1,95 @@
+/// Appended to close a code block that continues in the next chunk.
+const CLOSING_FENCE: &str = "\n```";
+
+/// Splits `text` into chunks of at most `limit` characters.
+///
+/// Chunks end on a paragraph, line, sentence or word boundary when possible.
+/// A code block cut in half is closed at the end of its chunk and reopened,
+/// with its language, at the start of the next one.
+pub fn split_message(text: &str, limit: usize) -> Vec<String> {
+    let mut chunks: Vec<String> = Vec::new();
+    let mut rest = text.to_string();
+    let mut reopen: Option<String> = None;
+
+    loop {
+        let prefix = match reopen.take() {
+            Some(fence) => fence + "\n",
+            None => String::new(),
+        };
+        let candidate = prefix.clone() + &rest;
+        if candidate.chars().count() <= limit {
+            if !candidate.trim().is_empty() {
+                chunks.push(candidate);
+            }
+            break;
+        }
+
+        let prefix_length = prefix.chars().count();
+        let budget = limit
+            .saturating_sub(CLOSING_FENCE.len())
+            .max(prefix_length + 1);
+        let end = byte_index(&candidate, budget);
+        let cut = break_index(&candidate[prefix.len()..end]) + prefix.len();
+
+        let (head, tail) = candidate.split_at(cut);
+        let fence = open_fence(head);
+        let mut head = head.trim_end().to_string();
+        if fence.is_some() {
+            head.push_str(CLOSING_FENCE);
+            rest = tail.trim_start_matches('\n').to_string();
+        } else {
+            rest = tail.trim_start().to_string();
+        }
+        if !head.trim().is_empty() {
+            chunks.push(head);
+        }
+        reopen = fence;
+    }
+
+    chunks
+}
+
+/// Byte index of the character at `chars`, or the length of `text`.
+fn byte_index(text: &str, chars: usize) -> usize {
+    match text.char_indices().nth(chars) {
+        Some((index, _)) => index,
+        None => text.len(),
+    }
+}
+
+/// Byte index to end a chunk of `text` at, preferring the last paragraph,
+/// line, sentence and word boundary in that order.
+fn break_index(text: &str) -> usize {
+    if let Some(index) = text.rfind("\n\n").filter(|index| *index > 0) {
+        return index;
+    }
+    if let Some(index) = text.rfind('\n').filter(|index| *index > 0) {
+        return index;
+    }
+    let sentence = [". ", "! ", "? "]
+        .iter()
+        .filter_map(|end| text.rfind(end))
+        .max();
+    if let Some(index) = sentence {
+        return index + 1;
+    }
+    if let Some(index) = text.rfind(' ').filter(|index| *index > 0) {
+        return index;
+    }
+    text.len()
+}
+
+/// Returns the opening line of the code block left open at the end of `text`.
+fn open_fence(text: &str) -> Option<String> {
+    let mut fence: Option<String> = None;
+    for line in text.lines() {
+        let line = line.trim();
+        if line.starts_with("```") {
+            fence = match fence {
+                Some(_) => None,
+                None => Some(line.to_string()),
+            };
+        }
+    }
+    fence
+}
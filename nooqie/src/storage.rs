@@ -0,0 +1,136 @@
+use rusqlite::Connection;
+
+use std::{
+    path::Path,
+    sync::{Arc, Mutex, MutexGuard},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Schema changes, applied in order. `PRAGMA user_version` records how many
+/// have been applied so far, so migrations must only ever be appended.
+const MIGRATIONS: &[&str] = &[
+    "
+    CREATE TABLE guild_settings (
+        guild_id INTEGER PRIMARY KEY,
+        prefix TEXT,
+        model TEXT,
+        persona TEXT
+    );
+    CREATE TABLE personas (
+        guild_id INTEGER NOT NULL,
+        name TEXT NOT NULL,
+        prompt TEXT NOT NULL,
+        PRIMARY KEY (guild_id, name)
+    );
+    CREATE TABLE channel_personas (
+        channel_id INTEGER PRIMARY KEY,
+        guild_id INTEGER NOT NULL,
+        persona TEXT NOT NULL
+    );
+    CREATE TABLE conversations (
+        channel_id INTEGER PRIMARY KEY,
+        last_active INTEGER NOT NULL
+    );
+    CREATE TABLE conversation_messages (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        channel_id INTEGER NOT NULL
+            REFERENCES conversations (channel_id) ON DELETE CASCADE,
+        role TEXT NOT NULL,
+        content TEXT NOT NULL
+    );
+    CREATE TABLE playlists (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        guild_id INTEGER NOT NULL,
+        name TEXT NOT NULL,
+        owner_id INTEGER NOT NULL,
+        UNIQUE (guild_id, name)
+    );
+    CREATE TABLE playlist_tracks (
+        playlist_id INTEGER NOT NULL
+            REFERENCES playlists (id) ON DELETE CASCADE,
+        position INTEGER NOT NULL,
+        url TEXT NOT NULL,
+        title TEXT,
+        PRIMARY KEY (playlist_id, position)
+    );
+",
+    "
+    ALTER TABLE guild_settings ADD COLUMN volume INTEGER;
+",
+    "
+    CREATE TABLE replies (
+        message_id INTEGER PRIMARY KEY,
+        guild_id INTEGER,
+        channel_id INTEGER NOT NULL,
+        model TEXT NOT NULL,
+        prompt TEXT NOT NULL,
+        messages TEXT NOT NULL,
+        answer TEXT NOT NULL,
+        created INTEGER NOT NULL
+    );
+    CREATE TABLE feedback (
+        message_id INTEGER NOT NULL
+            REFERENCES replies (message_id) ON DELETE CASCADE,
+        user_id INTEGER NOT NULL,
+        rating TEXT NOT NULL,
+        rated INTEGER NOT NULL,
+        PRIMARY KEY (message_id, user_id)
+    );
+",
+];
+
+/// Embedded SQLite database holding everything that has to survive a
+/// restart. Cloning it shares the same connection.
+#[derive(Clone)]
+pub struct Storage {
+    connection: Arc<Mutex<Connection>>,
+}
+
+impl Storage {
+    /// Opens or creates the database file at `path` and migrates it.
+    pub fn open(path: &Path) -> rusqlite::Result<Storage> {
+        Storage::new(Connection::open(path)?)
+    }
+
+    /// Opens a database that only lives as long as the returned `Storage`.
+    pub fn open_in_memory() -> rusqlite::Result<Storage> {
+        Storage::new(Connection::open_in_memory()?)
+    }
+
+    fn new(mut connection: Connection) -> rusqlite::Result<Storage> {
+        connection.pragma_update(None, "foreign_keys", true)?;
+        migrate(&mut connection)?;
+        Ok(Storage {
+            connection: Arc::new(Mutex::new(connection)),
+        })
+    }
+
+    /// Number of migrations applied to the database.
+    pub fn version(&self) -> rusqlite::Result<usize> {
+        self.connection()
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+    }
+
+    pub fn connection(&self) -> MutexGuard<'_, Connection> {
+        self.connection.lock().unwrap()
+    }
+}
+
+/// Milliseconds since the unix epoch, as timestamps are stored.
+pub fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as i64
+}
+
+fn migrate(connection: &mut Connection) -> rusqlite::Result<()> {
+    let version: usize = connection.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        let transaction = connection.transaction()?;
+        transaction.execute_batch(migration)?;
+        transaction.pragma_update(None, "user_version", index + 1)?;
+        transaction.commit()?;
+    }
+    Ok(())
+}
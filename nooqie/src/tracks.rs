@@ -0,0 +1,380 @@
+use poise::serenity_prelude::{prelude::TypeMapKey, ChannelId, UserId};
+
+use serde::Deserialize;
+
+use songbird::{input::AuxMetadata, tracks::TrackHandle};
+
+use rand::{seq::SliceRandom, Rng};
+
+use symphonia::core::{io::MediaSourceStream, probe::Hint};
+
+use std::{
+    collections::VecDeque,
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+/// Longest title shown in track lists before it is cut off.
+const MAX_TITLE_LENGTH: usize = 80;
+
+/// Extensions of audio files played as they are rather than through yt-dlp.
+pub const AUDIO_EXTENSIONS: &[&str] = &["mp3", "ogg", "opus", "flac", "wav"];
+
+/// Metadata of a queued track, kept in the typemap of its `TrackHandle`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TrackInfo {
+    pub title: String,
+    pub url: String,
+    pub duration: Option<Duration>,
+    pub thumbnail: Option<String>,
+    pub requester: UserId,
+    /// Channel the track was requested in, where it is announced.
+    pub channel_id: ChannelId,
+}
+
+pub struct TrackInfoKey;
+
+impl TypeMapKey for TrackInfoKey {
+    type Value = Arc<TrackInfo>;
+}
+
+impl TrackInfo {
+    /// Builds the track info from what yt-dlp reported about `url`, falling
+    /// back to the URL itself when there is no title.
+    pub fn from_metadata(
+        metadata: AuxMetadata,
+        url: &str,
+        requester: UserId,
+        channel_id: ChannelId,
+    ) -> Self {
+        TrackInfo {
+            title: metadata
+                .title
+                .or(metadata.track)
+                .unwrap_or_else(|| url.to_string()),
+            url: metadata.source_url.unwrap_or_else(|| url.to_string()),
+            duration: metadata.duration,
+            thumbnail: metadata.thumbnail,
+            requester,
+            channel_id,
+        }
+    }
+
+    /// Markdown link to the track, just its title when it is a local file.
+    pub fn link(&self, max: usize) -> String {
+        let title = truncate(&self.title.replace(['[', ']'], ""), max);
+        if is_url(&self.url) {
+            format!("[{title}]({})", self.url)
+        } else {
+            title
+        }
+    }
+
+    /// Markdown link to the track with its duration and requester.
+    pub fn describe(&self) -> String {
+        let duration = match self.duration {
+            Some(duration) => format_duration(duration),
+            None => String::from("live"),
+        };
+        format!(
+            "{} `{duration}` <@{}>",
+            self.link(MAX_TITLE_LENGTH),
+            self.requester
+        )
+    }
+}
+
+/// Whether `query` given to `play` is a link rather than search terms.
+pub fn is_url(query: &str) -> bool {
+    query.starts_with("https://") || query.starts_with("http://")
+}
+
+/// Where `play` gets a track from, picked by looking at what it was given.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Source {
+    /// Playlist link, its entries are queued through yt-dlp.
+    Playlist(String),
+    /// Link straight to an audio file.
+    Audio(String),
+    /// Any other link, left to yt-dlp.
+    YtDlp(String),
+    /// File of the soundboard directory.
+    Sound(PathBuf),
+    /// Search terms for YouTube.
+    Search(String),
+}
+
+impl Source {
+    /// Picks the source of `query`, sounds are looked up in `soundboard`.
+    pub fn of(query: &str, soundboard: Option<&Path>) -> Source {
+        let query = query.trim();
+        if is_url(query) {
+            if is_playlist(query) {
+                Source::Playlist(query.to_string())
+            } else if is_audio_url(query) {
+                Source::Audio(query.to_string())
+            } else {
+                Source::YtDlp(query.to_string())
+            }
+        } else if let Some(sound) = soundboard.and_then(|dir| find_sound(dir, query)) {
+            Source::Sound(sound)
+        } else {
+            Source::Search(query.to_string())
+        }
+    }
+}
+
+/// Whether `name` has the extension of an audio file played directly.
+pub fn is_audio_file(name: &str) -> bool {
+    Path::new(name)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            AUDIO_EXTENSIONS
+                .iter()
+                .any(|audio| extension.eq_ignore_ascii_case(audio))
+        })
+}
+
+/// Whether `url` links straight to an audio file.
+pub fn is_audio_url(url: &str) -> bool {
+    reqwest::Url::parse(url).is_ok_and(|url| is_audio_file(url.path()))
+}
+
+/// Name of the file `url` links to, or the URL itself.
+pub fn url_file_name(url: &str) -> String {
+    reqwest::Url::parse(url)
+        .ok()
+        .and_then(|url| {
+            let name = url.path_segments()?.next_back()?.to_string();
+            (!name.is_empty()).then_some(name)
+        })
+        .unwrap_or_else(|| url.to_string())
+}
+
+/// Audio files in `dir`, sorted by name.
+pub fn sounds(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut sounds: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && is_audio_file(&path.to_string_lossy()))
+        .collect();
+    sounds.sort();
+    sounds
+}
+
+/// Name a sound is played by, its file name without the extension.
+pub fn sound_name(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Looks up the sound called `name` in `dir`, ignoring case. Only files in
+/// the directory itself are considered, so `name` can't escape it.
+pub fn find_sound(dir: &Path, name: &str) -> Option<PathBuf> {
+    sounds(dir).into_iter().find(|path| {
+        sound_name(path).eq_ignore_ascii_case(name)
+            || path
+                .file_name()
+                .is_some_and(|file| file.to_string_lossy().eq_ignore_ascii_case(name))
+    })
+}
+
+/// Reads the duration of the audio file at `path` from its header.
+pub fn probe_duration(path: &Path) -> Option<Duration> {
+    let file = fs::File::open(path).ok()?;
+    let stream = MediaSourceStream::new(Box::new(file), Default::default());
+    let mut hint = Hint::new();
+    if let Some(extension) = path.extension().and_then(|extension| extension.to_str()) {
+        hint.with_extension(extension);
+    }
+    let probed = symphonia::default::get_probe()
+        .format(&hint, stream, &Default::default(), &Default::default())
+        .ok()?;
+    let track = probed.format.default_track()?;
+    let time_base = track.codec_params.time_base?;
+    let frames = track.codec_params.n_frames?;
+    let time = time_base.calc_time(frames);
+    Some(Duration::from_secs(time.seconds) + Duration::from_secs_f64(time.frac))
+}
+
+/// Whether `url` points at a whole playlist rather than a single video, a
+/// video opened from a playlist still counts as a single video.
+pub fn is_playlist(url: &str) -> bool {
+    let Ok(url) = reqwest::Url::parse(url) else {
+        return false;
+    };
+    let mut pairs = url.query_pairs();
+    url.path() == "/playlist"
+        || (pairs.clone().any(|(key, _)| key == "list") && !pairs.any(|(key, _)| key == "v"))
+}
+
+/// Playlist as printed by `yt-dlp --flat-playlist -J`, the entries are not
+/// resolved to streams yet.
+#[derive(Deserialize, Debug)]
+pub struct FlatPlaylist {
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub entries: Vec<FlatEntry>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct FlatEntry {
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(default)]
+    pub url: Option<String>,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub duration: Option<f64>,
+}
+
+impl FlatEntry {
+    /// Link to the entry, built from its id when yt-dlp only gave that.
+    pub fn url(&self) -> Option<String> {
+        match (&self.url, &self.id) {
+            (Some(url), _) if is_url(url) => Some(url.clone()),
+            (_, Some(id)) => Some(format!("https://www.youtube.com/watch?v={id}")),
+            _ => None,
+        }
+    }
+
+    pub fn metadata(&self) -> AuxMetadata {
+        AuxMetadata {
+            title: self.title.clone(),
+            source_url: self.url(),
+            duration: self
+                .duration
+                .filter(|duration| duration.is_finite() && *duration >= 0.0)
+                .map(Duration::from_secs_f64),
+            ..Default::default()
+        }
+    }
+}
+
+/// Cuts `text` down to `max` characters, ending with `…` when shortened.
+pub fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    let mut truncated: String = text.chars().take(max.saturating_sub(1)).collect();
+    truncated.push('…');
+    truncated
+}
+
+/// Returns the info stored in the typemap of `handle`, if any.
+pub async fn track_info(handle: &TrackHandle) -> Option<Arc<TrackInfo>> {
+    handle.typemap().read().await.get::<TrackInfoKey>().cloned()
+}
+
+/// Formats `duration` as `m:ss`, or `h:mm:ss` for an hour or more.
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+        format!("{hours}:{minutes:02}:{seconds:02}")
+    } else {
+        format!("{minutes}:{seconds:02}")
+    }
+}
+
+/// Parses a position like `90`, `1:30` or `1:02:03`.
+pub fn parse_timestamp(text: &str) -> Option<Duration> {
+    let parts: Vec<&str> = text.trim().split(':').collect();
+    if parts.len() > 3 {
+        return None;
+    }
+    let mut seconds: u64 = 0;
+    for (index, part) in parts.iter().enumerate() {
+        let value: u64 = part.parse().ok()?;
+        if index > 0 && value >= 60 {
+            return None;
+        }
+        seconds = seconds.checked_mul(60)?.checked_add(value)?;
+    }
+    Some(Duration::from_secs(seconds))
+}
+
+/// Text progress bar of `width` segments with the elapsed and total time.
+pub fn progress_bar(position: Duration, duration: Option<Duration>, width: usize) -> String {
+    let Some(duration) = duration.filter(|duration| !duration.is_zero()) else {
+        return format!("🔴 {} (live)", format_duration(position));
+    };
+    let position = position.min(duration);
+    let marker = (position.as_secs_f64() / duration.as_secs_f64() * width as f64) as usize;
+    let bar: String = (0..=width)
+        .map(|segment| if segment == marker { '🔘' } else { '▬' })
+        .collect();
+    format!(
+        "{bar} {} / {}",
+        format_duration(position),
+        format_duration(duration)
+    )
+}
+
+/// Renders a queue, the current track first, as pages of `per_page` tracks.
+/// Tracks without info are shown as unknown.
+pub fn queue_pages(tracks: &[Option<Arc<TrackInfo>>], per_page: usize) -> Vec<String> {
+    let describe = |track: &Option<Arc<TrackInfo>>| match track {
+        Some(track) => track.describe(),
+        None => String::from("unknown track"),
+    };
+    let Some((current, upcoming)) = tracks.split_first() else {
+        return vec![String::from("the queue is empty")];
+    };
+
+    let total: Duration = tracks
+        .iter()
+        .flatten()
+        .filter_map(|track| track.duration)
+        .sum();
+    let header = format!(
+        "**Now playing:** {}\n{} tracks, {} total",
+        describe(current),
+        tracks.len(),
+        format_duration(total)
+    );
+    if upcoming.is_empty() {
+        return vec![header];
+    }
+
+    upcoming
+        .chunks(per_page.max(1))
+        .enumerate()
+        .map(|(page, chunk)| {
+            let mut text = format!("{header}\n\n**Up next:**");
+            for (index, track) in chunk.iter().enumerate() {
+                let position = page * per_page.max(1) + index + 1;
+                text.push_str(&format!("\n`{position}.` {}", describe(track)));
+            }
+            text
+        })
+        .collect()
+}
+
+/// Moves the entry at `from` to `to`, shifting the ones in between. Returns
+/// false when either position is out of range.
+pub fn move_entry<T>(queue: &mut VecDeque<T>, from: usize, to: usize) -> bool {
+    if from >= queue.len() || to >= queue.len() {
+        return false;
+    }
+    if let Some(entry) = queue.remove(from) {
+        queue.insert(to, entry);
+    }
+    true
+}
+
+/// Shuffles every entry but the first one, which is currently playing.
+pub fn shuffle_upcoming<T>(queue: &mut VecDeque<T>, rng: &mut impl Rng) {
+    if queue.len() > 2 {
+        queue.make_contiguous()[1..].shuffle(rng);
+    }
+}
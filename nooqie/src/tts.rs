@@ -0,0 +1,110 @@
+use crate::NooqieError;
+
+use std::process::Stdio;
+
+use tokio::{io::AsyncWriteExt, process::Command};
+
+/// Turns a markdown answer into plain text worth reading out: code blocks
+/// are left out, links are read by their text and formatting is dropped.
+/// Longer text is cut at the last sentence that fits in `max` characters.
+pub fn speakable(text: &str, max: usize) -> String {
+    let mut plain = String::new();
+    let mut in_code = false;
+    for line in text.lines() {
+        if line.trim_start().starts_with("```") {
+            in_code = !in_code;
+            continue;
+        }
+        if in_code {
+            continue;
+        }
+        let line = line.trim_start_matches(['#', '>', ' ']);
+        plain.push_str(&strip_links(line));
+        plain.push(' ');
+    }
+
+    let plain: String = plain
+        .chars()
+        .filter(|c| !matches!(c, '*' | '_' | '~' | '`' | '|'))
+        .collect();
+    let plain = plain.split_whitespace().collect::<Vec<_>>().join(" ");
+    if plain.chars().count() <= max {
+        return plain;
+    }
+
+    let cut: String = plain.chars().take(max).collect();
+    match cut.rfind(['.', '!', '?']) {
+        Some(end) if end > 0 => cut[..=end].to_string(),
+        _ => cut,
+    }
+}
+
+/// Replaces `[text](url)` with `text` and bare links with "a link".
+fn strip_links(line: &str) -> String {
+    let mut result = String::new();
+    let mut rest = line;
+    while let Some(start) = rest.find('[') {
+        let Some((text, after)) = rest[start + 1..].split_once("](") else {
+            break;
+        };
+        let Some(end) = after.find(')') else {
+            break;
+        };
+        result.push_str(&rest[..start]);
+        result.push_str(text);
+        rest = &after[end + 1..];
+    }
+    result.push_str(rest);
+
+    result
+        .split(' ')
+        .map(|word| {
+            let bare = word.trim_start_matches('<');
+            if bare.starts_with("https://") || bare.starts_with("http://") {
+                "a link"
+            } else {
+                word
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Runs the text-to-speech `command` with `text` on its stdin and returns
+/// the WAV audio it writes to stdout.
+pub async fn synthesize(command: &[String], text: &str) -> Result<Vec<u8>, NooqieError> {
+    let (program, args) = command
+        .split_first()
+        .ok_or_else(|| NooqieError::Tts(String::from("no command configured")))?;
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|error| NooqieError::Tts(format!("{program}: {error}")))?;
+
+    // Written from another task so a program that starts writing audio
+    // before reading all the text can't block on a full stdout pipe.
+    if let Some(mut stdin) = child.stdin.take() {
+        let text = text.to_string();
+        tokio::spawn(async move {
+            let _ = stdin.write_all(text.as_bytes()).await;
+        });
+    }
+
+    let output = child
+        .wait_with_output()
+        .await
+        .map_err(|error| NooqieError::Tts(format!("{program}: {error}")))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let reason = stderr.lines().last().unwrap_or("unknown error");
+        return Err(NooqieError::Tts(format!("{program}: {reason}")));
+    }
+    if output.stdout.is_empty() {
+        return Err(NooqieError::Tts(format!("{program} produced no audio")));
+    }
+    Ok(output.stdout)
+}
@@ -0,0 +1,43 @@
+use crate::storage::Storage;
+
+use poise::serenity_prelude::GuildId;
+
+use rusqlite::{params, OptionalExtension};
+
+/// Volume of guilds that never changed it, in percent.
+pub const DEFAULT_VOLUME: u32 = 100;
+
+/// Playback volume chosen per guild, in percent.
+#[derive(Clone)]
+pub struct Volumes {
+    storage: Storage,
+}
+
+impl Volumes {
+    pub fn new(storage: Storage) -> Self {
+        Volumes { storage }
+    }
+
+    /// Returns the volume of `guild_id`, `DEFAULT_VOLUME` if it was never set.
+    pub fn get(&self, guild_id: GuildId) -> rusqlite::Result<u32> {
+        let volume = self
+            .storage
+            .connection()
+            .query_row(
+                "SELECT volume FROM guild_settings WHERE guild_id = ?1",
+                params![guild_id.get()],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(volume.flatten().unwrap_or(DEFAULT_VOLUME))
+    }
+
+    pub fn set(&self, guild_id: GuildId, volume: u32) -> rusqlite::Result<()> {
+        self.storage.connection().execute(
+            "INSERT INTO guild_settings (guild_id, volume) VALUES (?1, ?2)
+             ON CONFLICT (guild_id) DO UPDATE SET volume = excluded.volume",
+            params![guild_id.get(), volume],
+        )?;
+        Ok(())
+    }
+}
@@ -0,0 +1,106 @@
+#![cfg(test)]
+
+use nooqie::config::*;
+
+use poise::serenity_prelude::GuildId;
+
+use std::time::Duration;
+
+#[test]
+fn test_config_parse() {
+    let config = Config::parse(
+        r#"
+        [bot]
+        token = "token"
+        prefix = "?"
+
+        [ollama]
+        url = "http://ollama:11434/"
+        context_timeout = 60
+
+        [logging]
+        level = "debug"
+        "#,
+    )
+    .unwrap();
+    assert_eq!(config.bot.prefix, "?");
+    assert_eq!(config.ollama.model, "llama2-uncensored");
+    assert_eq!(config.ollama.context_timeout(), Duration::from_secs(60));
+    assert_eq!(config.ollama.max_turns(), Some(20));
+    assert_eq!(config.voice.max_queue, 100);
+    assert_eq!(config.voice.max_playlist, 50);
+    assert_eq!(config.voice.idle_timeout(), Some(Duration::from_secs(300)));
+    assert_eq!(config.tts.command, ["espeak-ng", "--stdout"]);
+    assert_eq!(config.listen.wake_word, "nooqie");
+    assert_eq!(config.listen.silence(), Duration::from_millis(800));
+    assert_eq!(config.limits.concurrency, 1);
+    assert_eq!(config.limits.cooldown(), Duration::from_secs(5));
+    assert_eq!(config.limits.guild_budget(), None);
+    assert_eq!(config.logging.level_filter(), Some(log::LevelFilter::Debug));
+    assert!(config.validate().is_ok());
+}
+
+#[test]
+fn test_config_unknown_field() {
+    assert!(Config::parse("[bot]\ntokn = \"token\"").is_err());
+}
+
+#[test]
+fn test_config_validate() {
+    let mut config = Config::parse("[bot]\ntoken = \"token\"").unwrap();
+    assert!(config.validate().is_ok());
+    config.ollama.url = String::from("ollama:11434");
+    assert!(config.validate().is_err());
+    config.ollama.url = String::from("https://ollama");
+    config.logging.level = Some(String::from("loud"));
+    assert!(config.validate().is_err());
+    config.logging.level = None;
+    config.limits.concurrency = 0;
+    assert!(config.validate().is_err());
+    config.limits.concurrency = 1;
+    config.bot.token = String::new();
+    assert!(config.validate().is_err());
+}
+
+#[test]
+fn test_config_guild_backend() {
+    let config = Config::parse(
+        r#"
+        [bot]
+        token = "token"
+
+        [backends.vllm]
+        api = "openai"
+        url = "http://vllm:8000/v1"
+        model = "meta-llama/Llama-3-8B"
+
+        [guilds.1234]
+        backend = "vllm"
+        "#,
+    )
+    .unwrap();
+    assert!(config.validate().is_ok());
+
+    let vllm = config.backend(Some(GuildId::new(1234)));
+    assert_eq!(vllm.api, Api::OpenAi);
+    assert_eq!(vllm.url, "http://vllm:8000/v1");
+    assert_eq!(vllm.model.as_deref(), Some("meta-llama/Llama-3-8B"));
+
+    let default = config.backend(Some(GuildId::new(1)));
+    assert_eq!(default.api, Api::Ollama);
+    assert_eq!(default.url, config.ollama.url);
+    assert_eq!(default.model.as_deref(), Some("llama2-uncensored"));
+    assert_eq!(config.backend(None), default);
+
+    let mut invalid = config.clone();
+    invalid.guilds.get_mut("1234").unwrap().backend = Some(String::from("tgi"));
+    assert!(invalid.validate().is_err());
+    let mut invalid = config.clone();
+    invalid
+        .guilds
+        .insert(String::from("home"), GuildConfig::default());
+    assert!(invalid.validate().is_err());
+    let mut invalid = config;
+    invalid.backends.get_mut("vllm").unwrap().url = String::from("vllm:8000");
+    assert!(invalid.validate().is_err());
+}
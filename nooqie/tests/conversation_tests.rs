@@ -0,0 +1,127 @@
+#![cfg(test)]
+
+use nooqie::{conversation::*, storage::Storage};
+
+use poise::serenity_prelude::ChannelId;
+
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(600);
+
+#[test]
+fn test_conversation_keeps_history_per_channel() {
+    let conversations = Conversations::new(Storage::open_in_memory().unwrap());
+    conversations
+        .record(ChannelId::new(1), "hello", "hi", TIMEOUT, None)
+        .unwrap();
+    assert_eq!(
+        conversations.history(ChannelId::new(1), TIMEOUT).unwrap(),
+        vec![
+            Message::new(Role::User, "hello"),
+            Message::new(Role::Assistant, "hi")
+        ]
+    );
+    assert!(conversations
+        .history(ChannelId::new(2), TIMEOUT)
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn test_conversation_reset() {
+    let conversations = Conversations::new(Storage::open_in_memory().unwrap());
+    conversations
+        .record(ChannelId::new(1), "hello", "hi", TIMEOUT, None)
+        .unwrap();
+    assert!(conversations.reset(ChannelId::new(1), TIMEOUT).unwrap());
+    assert!(!conversations.reset(ChannelId::new(1), TIMEOUT).unwrap());
+    assert!(conversations
+        .history(ChannelId::new(1), TIMEOUT)
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn test_conversation_expires() {
+    let conversations = Conversations::new(Storage::open_in_memory().unwrap());
+    conversations
+        .record(ChannelId::new(1), "hello", "hi", TIMEOUT, None)
+        .unwrap();
+    assert!(conversations
+        .history(ChannelId::new(1), Duration::ZERO)
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn test_conversation_regenerate_replaces_answer() {
+    let conversations = Conversations::new(Storage::open_in_memory().unwrap());
+    conversations
+        .record(ChannelId::new(1), "hello", "hi", TIMEOUT, None)
+        .unwrap();
+    assert!(conversations
+        .revise(ChannelId::new(1), "hi", "hey there", TIMEOUT)
+        .unwrap());
+    assert_eq!(
+        conversations.history(ChannelId::new(1), TIMEOUT).unwrap(),
+        vec![
+            Message::new(Role::User, "hello"),
+            Message::new(Role::Assistant, "hey there")
+        ]
+    );
+    assert!(!conversations
+        .revise(ChannelId::new(1), "hi", "hello", TIMEOUT)
+        .unwrap());
+}
+
+#[test]
+fn test_conversation_continue_extends_latest_answer() {
+    let conversations = Conversations::new(Storage::open_in_memory().unwrap());
+    conversations
+        .record(ChannelId::new(1), "count", "one two", TIMEOUT, None)
+        .unwrap();
+    conversations
+        .record(ChannelId::new(1), "again", "one two", TIMEOUT, None)
+        .unwrap();
+    assert!(conversations
+        .revise(ChannelId::new(1), "one two", "one two three", TIMEOUT)
+        .unwrap());
+    assert_eq!(
+        conversations.history(ChannelId::new(1), TIMEOUT).unwrap(),
+        vec![
+            Message::new(Role::User, "count"),
+            Message::new(Role::Assistant, "one two"),
+            Message::new(Role::User, "again"),
+            Message::new(Role::Assistant, "one two three")
+        ]
+    );
+}
+
+#[test]
+fn test_conversation_forgets_oldest_turns() {
+    let conversations = Conversations::new(Storage::open_in_memory().unwrap());
+    for turn in ["one", "two", "three"] {
+        conversations
+            .record(ChannelId::new(1), turn, turn, TIMEOUT, Some(2))
+            .unwrap();
+    }
+    conversations
+        .record(ChannelId::new(2), "other", "other", TIMEOUT, Some(2))
+        .unwrap();
+    assert_eq!(
+        conversations.history(ChannelId::new(1), TIMEOUT).unwrap(),
+        vec![
+            Message::new(Role::User, "two"),
+            Message::new(Role::Assistant, "two"),
+            Message::new(Role::User, "three"),
+            Message::new(Role::Assistant, "three")
+        ]
+    );
+    assert_eq!(
+        conversations
+            .history(ChannelId::new(2), TIMEOUT)
+            .unwrap()
+            .len(),
+        2
+    );
+}
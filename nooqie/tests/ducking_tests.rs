@@ -0,0 +1,19 @@
+#![cfg(test)]
+
+use nooqie::ducking::Ducking;
+
+use poise::serenity_prelude::GuildId;
+
+#[test]
+fn test_ducking_lasts_until_the_last_speech_ends() {
+    let ducking = Ducking::default();
+    ducking.start(GuildId::new(1));
+    ducking.start(GuildId::new(1));
+    assert!(ducking.is_ducked(GuildId::new(1)));
+    assert!(!ducking.is_ducked(GuildId::new(2)));
+
+    assert!(!ducking.end(GuildId::new(1)));
+    assert!(ducking.is_ducked(GuildId::new(1)));
+    assert!(ducking.end(GuildId::new(1)));
+    assert!(!ducking.is_ducked(GuildId::new(1)));
+}
@@ -0,0 +1,44 @@
+#![cfg(test)]
+
+use nooqie::generations::Generations;
+
+use poise::serenity_prelude::{ChannelId, UserId};
+
+use std::time::Duration;
+
+#[tokio::test]
+async fn test_generations_forget_dropped() {
+    let generations = Generations::default();
+    let older = generations.start(ChannelId::new(1), UserId::new(1));
+    let latest = generations.start(ChannelId::new(1), UserId::new(1));
+    drop(latest);
+
+    assert!(generations.stop_latest(ChannelId::new(1), UserId::new(1)));
+    tokio::time::timeout(Duration::from_secs(1), older.stopped())
+        .await
+        .unwrap();
+
+    drop(older);
+    assert!(!generations.stop_latest(ChannelId::new(1), UserId::new(1)));
+}
+
+#[tokio::test]
+async fn test_generations_stop_latest() {
+    let generations = Generations::default();
+    let older = generations.start(ChannelId::new(1), UserId::new(1));
+    let latest = generations.start(ChannelId::new(1), UserId::new(1));
+    let other = generations.start(ChannelId::new(1), UserId::new(2));
+
+    assert!(!generations.stop_latest(ChannelId::new(2), UserId::new(1)));
+    assert!(generations.stop_latest(ChannelId::new(1), UserId::new(1)));
+    tokio::time::timeout(Duration::from_secs(1), latest.stopped())
+        .await
+        .unwrap();
+    for untouched in [&older, &other] {
+        assert!(
+            tokio::time::timeout(Duration::from_millis(50), untouched.stopped())
+                .await
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,58 @@
+#![cfg(test)]
+
+use nooqie::idle::IdleTimers;
+
+use poise::serenity_prelude::GuildId;
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+#[tokio::test]
+async fn test_idle_schedule() {
+    let timers = IdleTimers::default();
+    let guild = GuildId::new(1);
+    let left = Arc::new(AtomicBool::new(false));
+
+    let flag = left.clone();
+    timers.schedule(guild, Duration::from_millis(10), async move {
+        flag.store(true, Ordering::SeqCst);
+    });
+    assert!(timers.is_scheduled(guild));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(left.load(Ordering::SeqCst));
+    assert!(!timers.is_scheduled(guild));
+}
+
+#[tokio::test]
+async fn test_idle_cancel() {
+    let timers = IdleTimers::default();
+    let guild = GuildId::new(1);
+    let left = Arc::new(AtomicBool::new(false));
+
+    let flag = left.clone();
+    timers.schedule(guild, Duration::from_millis(10), async move {
+        flag.store(true, Ordering::SeqCst);
+    });
+    assert!(timers.cancel(guild));
+    assert!(!timers.cancel(guild));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(!left.load(Ordering::SeqCst));
+}
+
+#[test]
+fn test_idle_paused() {
+    let timers = IdleTimers::default();
+    let guild = GuildId::new(1);
+    assert!(!timers.take_paused(guild));
+    timers.mark_paused(guild);
+    assert!(timers.take_paused(guild));
+    assert!(!timers.take_paused(guild));
+    timers.mark_paused(guild);
+    timers.forget(guild);
+    assert!(!timers.take_paused(guild));
+}
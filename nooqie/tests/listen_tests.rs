@@ -0,0 +1,114 @@
+#![cfg(test)]
+
+use nooqie::listen::*;
+
+use poise::serenity_prelude::{ChannelId, GuildId, UserId};
+
+use std::time::Duration;
+
+/// 20 ms of audio at `SAMPLE_RATE`.
+const FRAME: usize = 320;
+
+fn utterances() -> Utterances {
+    Utterances::new(
+        500.0,
+        Duration::from_millis(100),
+        Duration::from_millis(60),
+        Duration::from_secs(1),
+    )
+}
+
+#[test]
+fn test_rms() {
+    assert_eq!(rms(&[]), 0.0);
+    assert_eq!(rms(&[3, -3, 3, -3]), 3.0);
+}
+
+#[test]
+fn test_utterance_ends_after_silence() {
+    let mut vad = utterances();
+    let (loud, quiet) = ([1000; FRAME], [0; FRAME]);
+    assert_eq!(vad.push(1, &quiet), None);
+    for _ in 0..5 {
+        assert_eq!(vad.push(1, &loud), None);
+    }
+    for _ in 0..4 {
+        assert_eq!(vad.push(1, &quiet), None);
+    }
+    let utterance = vad.push(1, &quiet).unwrap();
+    assert_eq!(utterance.len(), 10 * FRAME);
+
+    assert_eq!(vad.push(2, &loud), None);
+    assert_eq!(vad.silence(2, Duration::from_millis(60)), None);
+    assert_eq!(vad.silence(2, Duration::from_millis(60)), None);
+}
+
+#[test]
+fn test_utterance_limits() {
+    let mut vad = utterances();
+    let loud = [1000; FRAME];
+    let mut finished = None;
+    for _ in 0..50 {
+        finished = finished.or(vad.push(1, &loud));
+    }
+    assert_eq!(finished.unwrap().len(), 50 * FRAME);
+
+    assert_eq!(vad.push(1, &loud), None);
+    vad.forget(1);
+    assert_eq!(vad.silence(1, Duration::from_secs(1)), None);
+}
+
+#[test]
+fn test_wav() {
+    let wav = wav(&[1, -1]);
+    assert_eq!(wav.len(), 48);
+    assert_eq!(&wav[..4], b"RIFF");
+    assert_eq!(&wav[8..16], b"WAVEfmt ");
+    assert_eq!(&wav[24..28], &SAMPLE_RATE.to_le_bytes());
+    assert_eq!(&wav[44..], &[1, 0, 255, 255]);
+}
+
+#[test]
+fn test_strip_wake_word() {
+    assert_eq!(
+        strip_wake_word("Nooqie, what time is it?", "nooqie").as_deref(),
+        Some("what time is it?")
+    );
+    assert_eq!(
+        strip_wake_word("Hey nooqie... tell a joke", "nooqie").as_deref(),
+        Some("tell a joke")
+    );
+    assert_eq!(
+        strip_wake_word("ok computer play music", "OK computer").as_deref(),
+        Some("play music")
+    );
+    assert_eq!(strip_wake_word("Nooqie.", "nooqie").as_deref(), Some(""));
+    assert_eq!(strip_wake_word("I told nooqie hi", "nooqie"), None);
+    assert_eq!(strip_wake_word("", "nooqie"), None);
+}
+
+#[test]
+fn test_sessions() {
+    let sessions = Sessions::default();
+    let (guild, channel, user) = (GuildId::new(1), ChannelId::new(2), UserId::new(3));
+
+    let id = sessions.start(guild, channel).unwrap();
+    assert!(sessions.start(guild, channel).is_none());
+    assert!(sessions.is_active(guild));
+    assert_eq!(sessions.channel(guild, id), Some(channel));
+
+    assert!(!sessions.has_consented(guild, id, user));
+    assert!(sessions.set_consent(guild, id, user, true));
+    assert!(sessions.has_consented(guild, id, user));
+    assert!(sessions.set_consent(guild, id, user, false));
+    assert!(!sessions.has_consented(guild, id, user));
+
+    assert!(sessions.set_consent(guild, id, user, true));
+    assert!(sessions.stop(guild));
+    assert!(!sessions.stop(guild));
+    let next = sessions.start(guild, channel).unwrap();
+    assert_ne!(id, next);
+    assert_eq!(sessions.channel(guild, id), None);
+    assert!(!sessions.has_consented(guild, next, user));
+    assert!(!sessions.set_consent(guild, id, user, true));
+}
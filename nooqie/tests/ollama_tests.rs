@@ -1,17 +1,203 @@
 #![cfg(test)]
 
 use nooqie::commands::*;
+use nooqie::conversation::{Message, Role};
 
 #[test]
-fn test_json_strip_escape_valid_input() {
-    let _test_data = r#"nothing to strip"#;
-    let result = ollama::json_strip_escape(&_test_data).unwrap();
-    assert_eq!(result, r#"nothing to strip"#);
+fn test_chat_request_serializes_messages() {
+    let request = ollama::ChatRequest {
+        model: String::from("llama2-uncensored"),
+        messages: vec![
+            Message::new(Role::System, "be nice"),
+            Message::new(Role::User, r#""every/thing" to \strip"#),
+        ],
+        stream: false,
+        options: None,
+    };
+    let result = serde_json::to_string(&request).unwrap();
+    assert_eq!(
+        result,
+        r#"{"model":"llama2-uncensored","messages":[{"role":"system","content":"be nice"},{"role":"user","content":"\"every/thing\" to \\strip"}],"stream":false}"#
+    );
 }
 
 #[test]
-fn test_json_strip_escape_invalid_input() {
-    let _test_data = String::from(r#""every/thing" to \strip"#);
-    let result = ollama::json_strip_escape(&_test_data).unwrap();
-    assert_eq!(result, r#""every/thing" to \strip"#);
+fn test_chat_response_deserializes_message() {
+    let test_data = r#"{
+        "model": "llama2-uncensored",
+        "created_at": "2024-07-01T00:00:00Z",
+        "message": { "role": "assistant", "content": "pong" },
+        "done": true,
+        "done_reason": "stop",
+        "total_duration": 1,
+        "eval_count": 2
+    }"#;
+    let result: ollama::ChatResponse = serde_json::from_str(test_data).unwrap();
+    assert_eq!(result.message, Message::new(Role::Assistant, "pong"));
+    assert_eq!(result.eval_count, 2);
+}
+
+#[test]
+fn test_chat_response_deserializes_stream_chunk() {
+    let test_data = r#"{"model":"llama2-uncensored","created_at":"2024-07-01T00:00:00Z","message":{"role":"assistant","content":"po"},"done":false}"#;
+    let result: ollama::ChatResponse = serde_json::from_str(test_data).unwrap();
+    assert_eq!(result.message.content, "po");
+    assert!(!result.done);
+    assert_eq!(result.done_reason, None);
+}
+
+#[test]
+fn test_tags_response_deserializes_models() {
+    let test_data = r#"{"models":[{"name":"llama3:latest","model":"llama3:latest","size":4661224676,"details":{"format":"gguf","family":"llama","parameter_size":"8.0B","quantization_level":"Q4_0"}}]}"#;
+    let result: ollama::TagsResponse = serde_json::from_str(test_data).unwrap();
+    assert_eq!(result.models.len(), 1);
+    assert_eq!(result.models[0].name, "llama3:latest");
+    assert_eq!(result.models[0].details.parameter_size, "8.0B");
+}
+
+#[test]
+fn test_completion_chunk_deserializes_delta() {
+    let test_data = r#"{"id":"chatcmpl-1","object":"chat.completion.chunk","created":1719792000,"model":"llama3","choices":[{"index":0,"delta":{"role":"assistant","content":"po"},"finish_reason":null}]}"#;
+    let result: ollama::CompletionChunk = serde_json::from_str(test_data).unwrap();
+    assert_eq!(result.choices[0].delta.content.as_deref(), Some("po"));
+    assert_eq!(result.choices[0].finish_reason, None);
+
+    let test_data = r#"{"choices":[{"index":0,"delta":{},"finish_reason":"stop"}]}"#;
+    let result: ollama::CompletionChunk = serde_json::from_str(test_data).unwrap();
+    assert_eq!(result.choices[0].delta.content, None);
+    assert_eq!(result.choices[0].finish_reason.as_deref(), Some("stop"));
+}
+
+#[test]
+fn test_openai_models_deserializes_ids() {
+    let test_data = r#"{"object":"list","data":[{"id":"meta-llama/Llama-3-8B","object":"model","owned_by":"vllm"}]}"#;
+    let result: ollama::OpenAiModels = serde_json::from_str(test_data).unwrap();
+    assert_eq!(result.data[0].id, "meta-llama/Llama-3-8B");
+    assert_eq!(result.data[0].owned_by, "vllm");
+}
+
+#[test]
+fn test_chat_request_serializes_images() {
+    let request = ollama::ChatRequest {
+        model: String::from("llava"),
+        messages: vec![
+            Message::new(Role::User, "what's this?").with_images(vec![String::from("aGVsbG8=")])
+        ],
+        stream: true,
+        options: None,
+    };
+    let result = serde_json::to_string(&request).unwrap();
+    assert_eq!(
+        result,
+        r#"{"model":"llava","messages":[{"role":"user","content":"what's this?","images":["aGVsbG8="]}],"stream":true}"#
+    );
+}
+
+#[test]
+fn test_chat_request_serializes_seed() {
+    let request = ollama::ChatRequest {
+        model: String::from("llama3"),
+        messages: Vec::new(),
+        stream: true,
+        options: Some(ollama::ChatOptions { seed: 42 }),
+    };
+    let result = serde_json::to_string(&request).unwrap();
+    assert_eq!(
+        result,
+        r#"{"model":"llama3","messages":[],"stream":true,"options":{"seed":42}}"#
+    );
+}
+
+#[test]
+fn test_completion_request_sends_images_as_content_parts() {
+    let request = ollama::CompletionRequest {
+        model: String::from("llava"),
+        messages: vec![
+            Message::new(Role::System, "be nice").into(),
+            Message::new(Role::User, "what's this?")
+                .with_images(vec![String::from("/9j/4AAQ")])
+                .into(),
+        ],
+        stream: true,
+        seed: Some(42),
+    };
+    let result = serde_json::to_string(&request).unwrap();
+    assert_eq!(
+        result,
+        r#"{"model":"llava","messages":[{"role":"system","content":"be nice"},{"role":"user","content":[{"type":"text","text":"what's this?"},{"type":"image_url","image_url":{"url":"data:image/jpeg;base64,/9j/4AAQ"}}]}],"stream":true,"seed":42}"#
+    );
+}
+
+#[test]
+fn test_show_response_supports_vision() {
+    let test_data = r#"{"parameters":"","template":"","details":{"family":"llama","families":["llama","clip"]}}"#;
+    let result: ollama::ShowResponse = serde_json::from_str(test_data).unwrap();
+    assert!(result.supports_vision());
+
+    let test_data =
+        r#"{"details":{"family":"gemma3","families":null},"capabilities":["completion","vision"]}"#;
+    let result: ollama::ShowResponse = serde_json::from_str(test_data).unwrap();
+    assert!(result.supports_vision());
+
+    let test_data = r#"{"details":{"family":"llama","families":["llama"]},"capabilities":["completion","tools"]}"#;
+    let result: ollama::ShowResponse = serde_json::from_str(test_data).unwrap();
+    assert!(!result.supports_vision());
+}
+
+#[test]
+fn test_is_image() {
+    assert!(ollama::is_image(Some("image/png")));
+    assert!(ollama::is_image(Some("image/jpeg; charset=binary")));
+    assert!(!ollama::is_image(Some("image/svg+xml")));
+    assert!(!ollama::is_image(Some("audio/mpeg")));
+    assert!(!ollama::is_image(None));
+}
+
+/// Names of the options of a slash command, or of its subcommands.
+fn slash_options(command: poise::Command<nooqie::Data, nooqie::Error>) -> Vec<String> {
+    let command = serde_json::to_value(command.create_as_slash_command().unwrap()).unwrap();
+    command["options"]
+        .as_array()
+        .map(|options| {
+            options
+                .iter()
+                .map(|option| option["name"].as_str().unwrap().to_owned())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[test]
+fn test_llm_slash_command_takes_prompt_and_image() {
+    assert_eq!(slash_options(ollama::llm()), vec!["prompt", "image"]);
+    assert_eq!(
+        slash_options(ollama::chat()),
+        vec!["reset", "history", "stop", "feedback"]
+    );
+}
+
+#[tokio::test]
+async fn test_openai_backend_checks_vision_from_config() {
+    let mut config = nooqie::config::BackendConfig {
+        api: nooqie::config::Api::OpenAi,
+        // Nothing listens there, the check must not ask the server.
+        url: String::from("http://127.0.0.1:9/v1"),
+        ..Default::default()
+    };
+    let images = vec![String::from("aW1hZ2U=")];
+
+    let backend = ollama::backend(&config, reqwest::Client::new());
+    assert!(matches!(
+        ollama::check_vision(backend.as_ref(), "llava", &images).await,
+        Err(nooqie::NooqieError::NoVision(model)) if model == "llava"
+    ));
+    assert!(ollama::check_vision(backend.as_ref(), "llava", &[])
+        .await
+        .is_ok());
+
+    config.vision = true;
+    let backend = ollama::backend(&config, reqwest::Client::new());
+    assert!(ollama::check_vision(backend.as_ref(), "llava", &images)
+        .await
+        .is_ok());
 }
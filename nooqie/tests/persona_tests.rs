@@ -0,0 +1,65 @@
+#![cfg(test)]
+
+use nooqie::{persona::*, storage::Storage};
+
+use poise::serenity_prelude::{ChannelId, GuildId};
+
+#[test]
+fn test_persona_channel_overrides_guild() {
+    let personas = Personas::new(Storage::open_in_memory().unwrap());
+    let guild_id = GuildId::new(1);
+    personas
+        .set(guild_id, "Pirate", "talk like a pirate")
+        .unwrap();
+    personas
+        .set(guild_id, "robot", "talk like a robot")
+        .unwrap();
+    assert!(personas.activate(guild_id, None, Some("pirate")).unwrap());
+    assert!(personas
+        .activate(guild_id, Some(ChannelId::new(2)), Some("robot"))
+        .unwrap());
+    assert_eq!(
+        personas.active(guild_id, ChannelId::new(2)).unwrap(),
+        Some((String::from("robot"), String::from("talk like a robot")))
+    );
+    assert_eq!(
+        personas.active(guild_id, ChannelId::new(3)).unwrap(),
+        Some((String::from("pirate"), String::from("talk like a pirate")))
+    );
+}
+
+#[test]
+fn test_persona_delete_deactivates() {
+    let personas = Personas::new(Storage::open_in_memory().unwrap());
+    let guild_id = GuildId::new(1);
+    personas
+        .set(guild_id, "pirate", "talk like a pirate")
+        .unwrap();
+    assert!(personas.activate(guild_id, None, Some("pirate")).unwrap());
+    assert!(personas.delete(guild_id, "pirate").unwrap());
+    assert_eq!(personas.active(guild_id, ChannelId::new(2)).unwrap(), None);
+    assert!(!personas.activate(guild_id, None, Some("pirate")).unwrap());
+}
+
+#[test]
+fn test_persona_channel_override_stays_in_its_guild() {
+    let personas = Personas::new(Storage::open_in_memory().unwrap());
+    for guild_id in [GuildId::new(1), GuildId::new(2)] {
+        personas
+            .set(guild_id, "pirate", "talk like a pirate")
+            .unwrap();
+        personas
+            .set(guild_id, "robot", "talk like a robot")
+            .unwrap();
+    }
+    assert!(personas
+        .activate(GuildId::new(1), Some(ChannelId::new(3)), Some("robot"))
+        .unwrap());
+    assert!(personas
+        .activate(GuildId::new(2), None, Some("pirate"))
+        .unwrap());
+    assert_eq!(
+        personas.active(GuildId::new(2), ChannelId::new(3)).unwrap(),
+        Some((String::from("pirate"), String::from("talk like a pirate")))
+    );
+}
@@ -0,0 +1,73 @@
+#![cfg(test)]
+
+use nooqie::conversation::{Message, Role};
+use nooqie::replies::*;
+use nooqie::storage::Storage;
+
+use poise::serenity_prelude::{ChannelId, GuildId, MessageId, UserId};
+
+fn reply(guild_id: u64) -> Reply {
+    Reply {
+        guild_id: Some(GuildId::new(guild_id)),
+        channel_id: ChannelId::new(1),
+        model: String::from("llama3"),
+        prompt: String::from("ping?"),
+        messages: vec![
+            Message::new(Role::User, "hello"),
+            Message::new(Role::Assistant, "hi"),
+            Message::new(Role::User, "ping?"),
+        ],
+        answer: String::from("pong"),
+    }
+}
+
+#[test]
+fn test_replies_record_and_get() {
+    let replies = Replies::new(Storage::open_in_memory().unwrap());
+    assert_eq!(replies.get(MessageId::new(1)).unwrap(), None);
+
+    replies.record(MessageId::new(1), &reply(1)).unwrap();
+    assert_eq!(replies.get(MessageId::new(1)).unwrap(), Some(reply(1)));
+}
+
+#[test]
+fn test_replies_rate() {
+    let replies = Replies::new(Storage::open_in_memory().unwrap());
+    assert!(!replies
+        .rate(MessageId::new(1), UserId::new(1), Rating::Up)
+        .unwrap());
+
+    replies.record(MessageId::new(1), &reply(1)).unwrap();
+    replies.record(MessageId::new(2), &reply(2)).unwrap();
+    assert!(replies
+        .rate(MessageId::new(1), UserId::new(1), Rating::Up)
+        .unwrap());
+    assert!(replies
+        .rate(MessageId::new(1), UserId::new(1), Rating::Down)
+        .unwrap());
+    assert!(replies
+        .rate(MessageId::new(2), UserId::new(1), Rating::Up)
+        .unwrap());
+
+    let feedback = replies.feedback(GuildId::new(1)).unwrap();
+    assert_eq!(feedback.len(), 1);
+    assert_eq!(feedback[0].rating, Rating::Down);
+    assert_eq!(feedback[0].answer, "pong");
+    assert_eq!(feedback[0].messages, reply(1).messages);
+    assert!(replies.feedback(GuildId::new(3)).unwrap().is_empty());
+}
+
+#[test]
+fn test_feedback_serializes_as_json_line() {
+    let feedback = Feedback {
+        model: String::from("llama3"),
+        messages: vec![Message::new(Role::User, "ping?")],
+        answer: String::from("pong"),
+        rating: Rating::Up,
+        rated_at: 1,
+    };
+    assert_eq!(
+        serde_json::to_string(&feedback).unwrap(),
+        r#"{"model":"llama3","messages":[{"role":"user","content":"ping?"}],"answer":"pong","rating":"up","rated_at":1}"#
+    );
+}
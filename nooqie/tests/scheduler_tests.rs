@@ -0,0 +1,114 @@
+#![cfg(test)]
+
+use nooqie::config::LimitsConfig;
+use nooqie::scheduler::{Refusal, Scheduler};
+
+use poise::serenity_prelude::{GuildId, UserId};
+
+use std::time::Duration;
+
+fn limits(concurrency: usize, cooldown: u64, guild_budget: u64) -> LimitsConfig {
+    LimitsConfig {
+        concurrency,
+        cooldown,
+        guild_budget,
+        budget_window: 3600,
+    }
+}
+
+#[test]
+fn test_scheduler_cooldown() {
+    let scheduler = Scheduler::default();
+    let limits = limits(1, 60, 0);
+    let guild = Some(GuildId::new(1));
+
+    let _ticket = scheduler.admit(UserId::new(1), guild, &limits).unwrap();
+    match scheduler.admit(UserId::new(1), guild, &limits) {
+        Err(Refusal::Cooldown(wait)) => assert!(wait <= Duration::from_secs(60)),
+        _ => panic!("expected a cooldown"),
+    }
+    assert!(scheduler.admit(UserId::new(2), guild, &limits).is_ok());
+
+    let limits = self::limits(1, 0, 0);
+    assert!(scheduler.admit(UserId::new(3), guild, &limits).is_ok());
+    assert!(scheduler.admit(UserId::new(3), guild, &limits).is_ok());
+}
+
+#[test]
+fn test_scheduler_refund_cooldown() {
+    let scheduler = Scheduler::default();
+    let limits = limits(1, 60, 0);
+
+    drop(scheduler.admit(UserId::new(1), None, &limits).unwrap());
+    assert!(scheduler.admit(UserId::new(1), None, &limits).is_err());
+    scheduler.refund(UserId::new(1));
+    assert!(scheduler.admit(UserId::new(1), None, &limits).is_ok());
+    assert!(scheduler.admit(UserId::new(1), None, &limits).is_err());
+}
+
+#[test]
+fn test_scheduler_guild_budget() {
+    let scheduler = Scheduler::default();
+    let limits = limits(1, 0, 100);
+    let guild = GuildId::new(1);
+
+    scheduler.record(guild, 60);
+    assert!(scheduler
+        .admit(UserId::new(1), Some(guild), &limits)
+        .is_ok());
+    scheduler.record(guild, 40);
+    assert!(matches!(
+        scheduler.admit(UserId::new(1), Some(guild), &limits),
+        Err(Refusal::BudgetSpent(_))
+    ));
+    assert!(scheduler
+        .admit(UserId::new(1), Some(GuildId::new(2)), &limits)
+        .is_ok());
+    assert!(scheduler.admit(UserId::new(1), None, &limits).is_ok());
+}
+
+#[test]
+fn test_scheduler_queue_positions() {
+    let scheduler = Scheduler::default();
+    let limits = limits(1, 0, 0);
+
+    let first = scheduler.admit(UserId::new(1), None, &limits).unwrap();
+    let second = scheduler.admit(UserId::new(2), None, &limits).unwrap();
+    let third = scheduler.admit(UserId::new(3), None, &limits).unwrap();
+    assert_eq!(third.position(), Some(3));
+    assert!(second.try_start(1).is_none());
+
+    let permit = first.try_start(1).unwrap();
+    assert_eq!(first.position(), None);
+    assert_eq!(second.position(), Some(1));
+    assert!(second.try_start(1).is_none());
+
+    drop(second);
+    assert_eq!(third.position(), Some(1));
+    drop(permit);
+    assert!(third.try_start(1).is_some());
+}
+
+#[tokio::test]
+async fn test_scheduler_start_waits_for_permit() {
+    let scheduler = Scheduler::default();
+    let limits = limits(1, 0, 0);
+
+    let first = scheduler.admit(UserId::new(1), None, &limits).unwrap();
+    let permit = first.start(1).await;
+    let second = scheduler.admit(UserId::new(2), None, &limits).unwrap();
+
+    let waiting = tokio::spawn(async move {
+        let _permit = second.start(1).await;
+    });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(!waiting.is_finished());
+
+    drop(permit);
+    tokio::time::timeout(Duration::from_secs(1), waiting)
+        .await
+        .unwrap()
+        .unwrap();
+    let third = scheduler.admit(UserId::new(3), None, &limits).unwrap();
+    assert!(third.try_start(1).is_some());
+}
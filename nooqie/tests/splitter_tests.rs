@@ -0,0 +1,57 @@
+#![cfg(test)]
+
+use nooqie::splitter::*;
+
+#[test]
+fn test_split_message_short_input() {
+    let result = split_message("nothing to split", 2000);
+    assert_eq!(result, vec![String::from("nothing to split")]);
+}
+
+#[test]
+fn test_split_message_paragraphs() {
+    let test_data = "first paragraph\n\nsecond paragraph";
+    let result = split_message(test_data, 24);
+    assert_eq!(result, vec!["first paragraph", "second paragraph"]);
+}
+
+#[test]
+fn test_split_message_sentences() {
+    let test_data = "One sentence here. Another sentence there.";
+    let result = split_message(test_data, 30);
+    assert_eq!(
+        result,
+        vec!["One sentence here.", "Another sentence there."]
+    );
+}
+
+#[test]
+fn test_split_message_multibyte_input() {
+    let test_data = "ü".repeat(25) + &"🦀".repeat(25);
+    let result = split_message(&test_data, 20);
+    assert!(result.iter().all(|chunk| chunk.chars().count() <= 20));
+    assert_eq!(result.concat(), test_data);
+}
+
+#[test]
+fn test_split_message_multibyte_words() {
+    let test_data = "日本語の文章 ".repeat(50);
+    let result = split_message(&test_data, 100);
+    assert!(result.len() > 1);
+    assert!(result.iter().all(|chunk| chunk.chars().count() <= 100));
+    assert!(result.iter().all(|chunk| chunk.starts_with('日')));
+}
+
+#[test]
+fn test_split_message_reopens_code_fence() {
+    let code: Vec<String> = (0..20).map(|i| format!("let x{i} = {i};")).collect();
+    let test_data = format!("Here you go:\n```rust\n{}\n```\nDone.", code.join("\n"));
+    let result = split_message(&test_data, 100);
+    assert!(result.len() > 1);
+    for chunk in &result {
+        assert!(chunk.chars().count() <= 100);
+        assert_eq!(chunk.matches("```").count() % 2, 0, "unbalanced: {chunk}");
+    }
+    assert!(result[1].starts_with("```rust\n"));
+    assert!(result.last().unwrap().ends_with("Done."));
+}
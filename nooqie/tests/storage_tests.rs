@@ -0,0 +1,75 @@
+#![cfg(test)]
+
+use nooqie::{models::Models, playlists::*, prefixes::Prefixes, storage::Storage, volumes::*};
+
+use poise::serenity_prelude::{GuildId, UserId};
+
+use std::{env, fs};
+
+#[test]
+fn test_storage_persists_across_restarts() {
+    let path = env::temp_dir().join(format!("nooqie-test-{}.db", std::process::id()));
+    let _ = fs::remove_file(&path);
+    {
+        let storage = Storage::open(&path).unwrap();
+        assert!(storage.version().unwrap() > 0);
+        Models::new(storage).set(GuildId::new(1), "llama3").unwrap();
+    }
+    let models = Models::new(Storage::open(&path).unwrap());
+    assert_eq!(
+        models.get(Some(GuildId::new(1))).unwrap(),
+        Some(String::from("llama3"))
+    );
+    assert_eq!(models.get(Some(GuildId::new(2))).unwrap(), None);
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_playlists() {
+    let playlists = Playlists::new(Storage::open_in_memory().unwrap());
+    let guild_id = GuildId::new(1);
+    let tracks = vec![
+        PlaylistTrack {
+            url: String::from("https://youtu.be/1"),
+            title: Some(String::from("one")),
+        },
+        PlaylistTrack {
+            url: String::from("https://youtu.be/2"),
+            title: None,
+        },
+    ];
+    playlists
+        .save(guild_id, UserId::new(2), "Mix", &tracks)
+        .unwrap();
+    assert_eq!(playlists.load(guild_id, "mix").unwrap(), Some(tracks));
+    assert_eq!(
+        playlists.list(guild_id).unwrap(),
+        vec![(String::from("mix"), 2)]
+    );
+    assert!(playlists.delete(guild_id, "mix").unwrap());
+    assert_eq!(playlists.load(guild_id, "mix").unwrap(), None);
+}
+
+#[test]
+fn test_prefixes() {
+    let prefixes = Prefixes::new(Storage::open_in_memory().unwrap());
+    let guild_id = GuildId::new(1);
+    assert_eq!(prefixes.get(Some(guild_id)).unwrap(), None);
+    prefixes.set(guild_id, Some("?")).unwrap();
+    assert_eq!(
+        prefixes.get(Some(guild_id)).unwrap(),
+        Some(String::from("?"))
+    );
+    assert_eq!(prefixes.get(None).unwrap(), None);
+    prefixes.set(guild_id, None).unwrap();
+    assert_eq!(prefixes.get(Some(guild_id)).unwrap(), None);
+}
+
+#[test]
+fn test_volumes() {
+    let volumes = Volumes::new(Storage::open_in_memory().unwrap());
+    let guild_id = GuildId::new(1);
+    assert_eq!(volumes.get(guild_id).unwrap(), DEFAULT_VOLUME);
+    volumes.set(guild_id, 150).unwrap();
+    assert_eq!(volumes.get(guild_id).unwrap(), 150);
+}
@@ -0,0 +1,229 @@
+#![cfg(test)]
+
+use nooqie::tracks::*;
+
+use poise::serenity_prelude::{ChannelId, UserId};
+
+use songbird::input::AuxMetadata;
+
+use std::{collections::VecDeque, sync::Arc, time::Duration};
+
+fn track(title: &str, seconds: u64) -> Option<Arc<TrackInfo>> {
+    Some(Arc::new(TrackInfo {
+        title: title.to_string(),
+        url: format!("https://youtu.be/{title}"),
+        duration: Some(Duration::from_secs(seconds)),
+        thumbnail: None,
+        requester: UserId::new(1),
+        channel_id: ChannelId::new(2),
+    }))
+}
+
+#[test]
+fn test_format_duration() {
+    assert_eq!(format_duration(Duration::from_secs(5)), "0:05");
+    assert_eq!(format_duration(Duration::from_secs(185)), "3:05");
+    assert_eq!(format_duration(Duration::from_secs(3723)), "1:02:03");
+}
+
+#[test]
+fn test_track_info_falls_back_to_url() {
+    let info = TrackInfo::from_metadata(
+        AuxMetadata::default(),
+        "https://youtu.be/x",
+        UserId::new(1),
+        ChannelId::new(2),
+    );
+    assert_eq!(info.title, "https://youtu.be/x");
+    assert_eq!(info.url, "https://youtu.be/x");
+    assert_eq!(
+        info.describe(),
+        "[https://youtu.be/x](https://youtu.be/x) `live` <@1>"
+    );
+}
+
+#[test]
+fn test_queue_pages() {
+    let tracks: Vec<_> = (0..6)
+        .map(|i| track(&format!("t{i}"), 60))
+        .chain([None])
+        .collect();
+    let pages = queue_pages(&tracks, 4);
+    assert_eq!(pages.len(), 2);
+    assert!(pages[0].starts_with("**Now playing:** [t0]"));
+    assert!(pages[0].contains("7 tracks, 6:00 total"));
+    assert!(pages[0].contains("`4.` [t4]"));
+    assert!(!pages[0].contains("`5.`"));
+    assert!(pages[1].contains("`5.` [t5]"));
+    assert!(pages[1].contains("`6.` unknown track"));
+    assert_eq!(
+        queue_pages(&[], 4),
+        vec![String::from("the queue is empty")]
+    );
+}
+
+#[test]
+fn test_is_url() {
+    assert!(is_url("https://www.youtube.com/watch?v=x"));
+    assert!(is_url("http://youtu.be/x"));
+    assert!(!is_url("darude sandstorm"));
+}
+
+#[test]
+fn test_truncate() {
+    assert_eq!(truncate("sandstorm", 9), "sandstorm");
+    assert_eq!(truncate("sandstorm", 5), "sand…");
+    assert_eq!(truncate("ääää", 3), "ää…");
+}
+
+#[test]
+fn test_is_playlist() {
+    assert!(is_playlist("https://www.youtube.com/playlist?list=PL1"));
+    assert!(is_playlist("https://music.youtube.com/watch?list=PL1"));
+    assert!(!is_playlist("https://www.youtube.com/watch?v=x&list=PL1"));
+    assert!(!is_playlist("https://youtu.be/x"));
+    assert!(!is_playlist("darude sandstorm"));
+}
+
+#[test]
+fn test_flat_playlist() {
+    let playlist: FlatPlaylist = serde_json::from_str(
+        r#"{
+            "title": "Mix",
+            "entries": [
+                {"id": "a", "url": "https://www.youtube.com/watch?v=a", "title": "A", "duration": 61.0},
+                {"id": "b", "url": "b", "title": "B", "duration": null},
+                {"title": "private video"}
+            ]
+        }"#,
+    )
+    .unwrap();
+    assert_eq!(playlist.title.as_deref(), Some("Mix"));
+    let urls: Vec<_> = playlist.entries.iter().map(|entry| entry.url()).collect();
+    assert_eq!(
+        urls,
+        vec![
+            Some(String::from("https://www.youtube.com/watch?v=a")),
+            Some(String::from("https://www.youtube.com/watch?v=b")),
+            None
+        ]
+    );
+    let metadata = playlist.entries[0].metadata();
+    assert_eq!(metadata.title.as_deref(), Some("A"));
+    assert_eq!(metadata.duration, Some(Duration::from_secs(61)));
+}
+
+#[test]
+fn test_move_entry() {
+    let mut queue: VecDeque<_> = (0..5).collect();
+    assert!(move_entry(&mut queue, 4, 1));
+    assert_eq!(queue, [0, 4, 1, 2, 3]);
+    assert!(move_entry(&mut queue, 1, 3));
+    assert_eq!(queue, [0, 1, 2, 4, 3]);
+    assert!(!move_entry(&mut queue, 1, 5));
+    assert_eq!(queue, [0, 1, 2, 4, 3]);
+}
+
+#[test]
+fn test_shuffle_keeps_current_track() {
+    let mut queue: VecDeque<_> = (0..20).collect();
+    shuffle_upcoming(&mut queue, &mut rand::thread_rng());
+    assert_eq!(queue[0], 0);
+    let mut sorted: Vec<_> = queue.into_iter().collect();
+    sorted.sort();
+    assert_eq!(sorted, (0..20).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_parse_timestamp() {
+    assert_eq!(parse_timestamp("90"), Some(Duration::from_secs(90)));
+    assert_eq!(parse_timestamp("1:30"), Some(Duration::from_secs(90)));
+    assert_eq!(parse_timestamp("1:02:03"), Some(Duration::from_secs(3723)));
+    assert_eq!(parse_timestamp("1:60"), None);
+    assert_eq!(parse_timestamp("1:2:3:4"), None);
+    assert_eq!(parse_timestamp("soon"), None);
+}
+
+#[test]
+fn test_progress_bar() {
+    let bar = progress_bar(Duration::from_secs(30), Some(Duration::from_secs(60)), 4);
+    assert_eq!(bar, "▬▬🔘▬▬ 0:30 / 1:00");
+    assert_eq!(
+        progress_bar(Duration::from_secs(5), None, 4),
+        "🔴 0:05 (live)"
+    );
+}
+
+#[test]
+fn test_describe_local_file() {
+    let mut info = track("airhorn", 3).unwrap().as_ref().clone();
+    info.url = String::from("airhorn");
+    assert_eq!(info.describe(), "airhorn `0:03` <@1>");
+}
+
+#[test]
+fn test_is_audio_file() {
+    assert!(is_audio_file("song.mp3"));
+    assert!(is_audio_file("Song.FLAC"));
+    assert!(is_audio_file("/path/voice.ogg"));
+    assert!(!is_audio_file("video.mp4"));
+    assert!(!is_audio_file("wav"));
+    assert!(is_audio_url(
+        "https://example.com/music/song.wav?download=1"
+    ));
+    assert!(!is_audio_url("https://www.youtube.com/watch?v=dQw4w9WgXcQ"));
+    assert_eq!(
+        url_file_name("https://example.com/music/song.wav?x=1"),
+        "song.wav"
+    );
+    assert_eq!(
+        url_file_name("https://example.com/"),
+        "https://example.com/"
+    );
+}
+
+#[test]
+fn test_source_of() {
+    let soundboard = std::env::temp_dir().join(format!("nooqie-sounds-{}", std::process::id()));
+    std::fs::create_dir_all(&soundboard).unwrap();
+    let airhorn = soundboard.join("Airhorn.wav");
+    std::fs::write(&airhorn, nooqie::listen::wav(&[0; 16_000])).unwrap();
+    std::fs::write(soundboard.join("notes.txt"), "not a sound").unwrap();
+
+    let dir = Some(soundboard.as_path());
+    assert_eq!(Source::of("airhorn", dir), Source::Sound(airhorn.clone()));
+    assert_eq!(
+        Source::of("AIRHORN.wav", dir),
+        Source::Sound(airhorn.clone())
+    );
+    assert_eq!(
+        Source::of("notes", dir),
+        Source::Search(String::from("notes"))
+    );
+    assert_eq!(
+        Source::of("../airhorn", dir),
+        Source::Search(String::from("../airhorn"))
+    );
+    assert_eq!(
+        Source::of("airhorn", None),
+        Source::Search(String::from("airhorn"))
+    );
+    assert_eq!(
+        Source::of("https://example.com/a.mp3", dir),
+        Source::Audio(String::from("https://example.com/a.mp3"))
+    );
+    assert_eq!(
+        Source::of("https://youtu.be/x", dir),
+        Source::YtDlp(String::from("https://youtu.be/x"))
+    );
+    assert_eq!(
+        Source::of("https://www.youtube.com/playlist?list=PL1", dir),
+        Source::Playlist(String::from("https://www.youtube.com/playlist?list=PL1"))
+    );
+
+    assert_eq!(sounds(&soundboard), vec![airhorn.clone()]);
+    assert_eq!(sound_name(&airhorn), "Airhorn");
+    assert_eq!(probe_duration(&airhorn), Some(Duration::from_secs(1)));
+
+    std::fs::remove_dir_all(&soundboard).unwrap();
+}
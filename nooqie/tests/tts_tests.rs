@@ -0,0 +1,32 @@
+#![cfg(test)]
+
+use nooqie::tts::*;
+
+#[test]
+fn test_speakable_markdown() {
+    let text = "# Answer\n**Rust** is _great_, see [the book](https://doc.rust-lang.org/book/).\n\
+                ```rust\nfn main() {}\n```\n> More at https://rust-lang.org";
+    assert_eq!(
+        speakable(text, 1000),
+        "Answer Rust is great, see the book. More at a link"
+    );
+}
+
+#[test]
+fn test_speakable_truncate() {
+    assert_eq!(speakable("One. Two. Three.", 12), "One. Two.");
+    assert_eq!(speakable("no sentence end here", 7), "no sent");
+    assert_eq!(speakable("   ", 10), "");
+}
+
+#[tokio::test]
+async fn test_synthesize() {
+    let command = vec![String::from("cat")];
+    assert_eq!(synthesize(&command, "hello").await.unwrap(), b"hello");
+
+    assert!(synthesize(&[String::from("false")], "hello").await.is_err());
+    assert!(synthesize(&[String::from("nooqie-no-such-tts")], "hello")
+        .await
+        .is_err());
+    assert!(synthesize(&[], "hello").await.is_err());
+}